@@ -0,0 +1,218 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+
+use crate::{FieldType, FormatError, LayoutField, Message, MessageData, Ulog};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PositionRedaction {
+    Keep,
+    #[default]
+    Zero,
+    // Shifts every position by a fixed offset in degrees, keeping the trajectory's shape.
+    Offset {
+        latitude: f64,
+        longitude: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Coordinate {
+    Latitude,
+    Longitude,
+    Altitude,
+}
+
+impl Coordinate {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.rsplit('.').next()? {
+            "lat" | "latitude_deg" | "ref_lat" => Some(Coordinate::Latitude),
+            "lon" | "longitude_deg" | "ref_lon" => Some(Coordinate::Longitude),
+            "alt" | "alt_ellipsoid" | "altitude_msl_m" | "altitude_ellipsoid_m" | "ref_alt" => {
+                Some(Coordinate::Altitude)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnonymizeOptions {
+    pub position_topics: HashSet<String>,
+    pub position: PositionRedaction,
+    pub info_patterns: Vec<String>,
+    pub parameters: HashSet<String>,
+}
+
+impl Default for AnonymizeOptions {
+    fn default() -> Self {
+        let topics = [
+            "vehicle_gps_position",
+            "sensor_gps",
+            "vehicle_global_position",
+            "estimator_global_position",
+            "vehicle_local_position",
+            "home_position",
+            "position_setpoint_triplet",
+            "mission_result",
+        ];
+        AnonymizeOptions {
+            position_topics: topics.into_iter().map(str::to_string).collect(),
+            position: PositionRedaction::default(),
+            info_patterns: vec!["uuid".to_string(), "serial".to_string()],
+            parameters: ["COM_FLIGHT_UUID", "LPE_LAT", "LPE_LON"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl AnonymizeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn position(mut self, position: PositionRedaction) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn position_topic(mut self, topic: impl Into<String>) -> Self {
+        self.position_topics.insert(topic.into());
+        self
+    }
+
+    pub fn info_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.info_patterns.push(pattern.into());
+        self
+    }
+
+    pub fn parameter(mut self, name: impl Into<String>) -> Self {
+        self.parameters.insert(name.into());
+        self
+    }
+
+    fn removes_info(&self, key: &str) -> bool {
+        let name = key.split_once(' ').map_or(key, |(_, name)| name);
+        self.info_patterns
+            .iter()
+            .any(|pattern| name.contains(pattern.as_str()))
+    }
+
+    fn removes_parameter(&self, key: &str) -> bool {
+        let name = key.split_once(' ').map_or(key, |(_, name)| name);
+        self.parameters.contains(name)
+    }
+
+    fn redact(&self, payload: &mut [u8], field: &LayoutField, coordinate: Coordinate) {
+        let Some(bytes) = payload.get_mut(field.offset..field.offset + field.size()) else {
+            return;
+        };
+        let offset = match (self.position, coordinate) {
+            (PositionRedaction::Keep, _) => return,
+            (PositionRedaction::Zero, _) => {
+                bytes.fill(0);
+                return;
+            }
+            (PositionRedaction::Offset { latitude, .. }, Coordinate::Latitude) => latitude,
+            (PositionRedaction::Offset { longitude, .. }, Coordinate::Longitude) => longitude,
+            (PositionRedaction::Offset { .. }, Coordinate::Altitude) => return,
+        };
+        match field.field_type {
+            // Integer coordinates are in 1e-7 degrees.
+            FieldType::Int32 => {
+                let value = i32::from_le_bytes(bytes[..4].try_into().unwrap());
+                let value = (f64::from(value) + offset * 1e7).round() as i32;
+                bytes.copy_from_slice(&value.to_le_bytes());
+            }
+            FieldType::Double => {
+                let value = f64::from_le_bytes(bytes[..8].try_into().unwrap());
+                bytes.copy_from_slice(&(value + offset).to_le_bytes());
+            }
+            FieldType::Float => {
+                let value = f32::from_le_bytes(bytes[..4].try_into().unwrap());
+                bytes.copy_from_slice(&(value + offset as f32).to_le_bytes());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Ulog {
+    pub fn anonymize(&self, options: &AnonymizeOptions) -> Result<Ulog, FormatError> {
+        let formats = self.formats()?;
+        let mut layouts: HashMap<Arc<str>, Arc<Vec<(LayoutField, Coordinate)>>> = HashMap::new();
+        let mut active: HashMap<u16, Arc<Vec<(LayoutField, Coordinate)>>> = HashMap::new();
+
+        let keep = |message: &Message| match message {
+            Message::Info(info) => !options.removes_info(&info.key),
+            Message::InfoMultiple(info) => !options.removes_info(&info.key),
+            Message::Parameter(parameter) => !options.removes_parameter(&parameter.key),
+            Message::ParameterDefault(parameter) => !options.removes_parameter(&parameter.key),
+            _ => true,
+        };
+        let definitions = self
+            .definitions
+            .iter()
+            .filter(|message| keep(message))
+            .cloned()
+            .collect();
+
+        let mut data = Vec::with_capacity(self.data.len());
+        for message in self.data.iter().filter(|message| keep(message)) {
+            match message {
+                Message::AddLogged(add_logged)
+                    if options.position_topics.contains(&*add_logged.message_name) =>
+                {
+                    let fields = match layouts.get(&add_logged.message_name) {
+                        Some(fields) => fields.clone(),
+                        None => {
+                            let layout = formats.layout(&add_logged.message_name)?;
+                            let fields: Vec<_> = layout
+                                .fields
+                                .into_iter()
+                                .filter(|field| field.array_len.is_none())
+                                .filter_map(|field| {
+                                    Coordinate::from_name(&field.name)
+                                        .map(|coordinate| (field, coordinate))
+                                })
+                                .collect();
+                            let fields = Arc::new(fields);
+                            layouts.insert(add_logged.message_name.clone(), fields.clone());
+                            fields
+                        }
+                    };
+                    active.insert(add_logged.msg_id, fields);
+                }
+                Message::AddLogged(add_logged) => {
+                    active.remove(&add_logged.msg_id);
+                }
+                Message::RemoveLogged(remove_logged) => {
+                    active.remove(&remove_logged.msg_id);
+                }
+                Message::Data(message_data) => {
+                    if let Some(fields) = active.get(&message_data.msg_id) {
+                        let mut payload = message_data.data.to_vec();
+                        for (field, coordinate) in fields.iter() {
+                            options.redact(&mut payload, field, *coordinate);
+                        }
+                        data.push(Message::Data(MessageData {
+                            data: Bytes::from(payload),
+                            ..message_data.clone()
+                        }));
+                        continue;
+                    }
+                }
+                // The referenced payload cannot be redacted, so it is left out.
+                Message::DataRef(data_ref) if active.contains_key(&data_ref.msg_id) => continue,
+                _ => {}
+            }
+            data.push(message.clone());
+        }
+        Ok(self.rewritten(definitions, data))
+    }
+}
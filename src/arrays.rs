@@ -0,0 +1,44 @@
+use ndarray::{Array1, Array2, Axis};
+
+use crate::{Column, Dataset};
+
+impl Column {
+    // Scalar numeric columns only; bools become 0 and 1.
+    pub fn to_array1(&self) -> Option<Array1<f64>> {
+        match self.array_len {
+            Some(_) => None,
+            None => self.data.to_f64_vec().map(Array1::from),
+        }
+    }
+
+    // One row per sample and one column per array element, so scalar columns give a
+    // single column.
+    pub fn to_array2(&self) -> Option<Array2<f64>> {
+        let values = self.data.to_f64_vec()?;
+        Array2::from_shape_vec((self.len(), self.stride()), values).ok()
+    }
+}
+
+impl Dataset {
+    pub fn timestamp_array(&self) -> Array1<u64> {
+        Array1::from(self.timestamps.clone())
+    }
+
+    pub fn array1(&self, name: &str) -> Option<Array1<f64>> {
+        self.column(name)?.to_array1()
+    }
+
+    // The columns side by side in the given order, with array fields expanded into
+    // one column per element. Returns None if a column is missing or not numeric.
+    pub fn array2(&self, names: &[&str]) -> Option<Array2<f64>> {
+        let arrays = names
+            .iter()
+            .map(|name| self.column(name)?.to_array2())
+            .collect::<Option<Vec<_>>>()?;
+        let views: Vec<_> = arrays.iter().map(|array| array.view()).collect();
+        if views.is_empty() {
+            return Some(Array2::zeros((self.len(), 0)));
+        }
+        ndarray::concatenate(Axis(1), &views).ok()
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    future::poll_fn,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::{Formats, Message, ParseOptions, Progress, UlogError, UlogErrorKind, UlogParser};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct AsyncUlogReader<R> {
+    reader: R,
+    chunk: Vec<u8>,
+    done: bool,
+    total_bytes: Option<usize>,
+    pub parser: UlogParser,
+}
+
+impl<R: AsyncRead + Unpin> AsyncUlogReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        AsyncUlogReader {
+            reader,
+            chunk: vec![0; CHUNK_SIZE],
+            done: false,
+            total_bytes: None,
+            parser: UlogParser::with_options(options),
+        }
+    }
+
+    pub fn with_total_bytes(mut self, total_bytes: usize) -> Self {
+        self.total_bytes = Some(total_bytes);
+        self
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.parser.offset(),
+            total_bytes: self.total_bytes,
+            messages: self.parser.message_count(),
+        }
+    }
+
+    pub fn formats(&self) -> &Formats {
+        self.parser.formats()
+    }
+
+    pub fn reset(&mut self, reader: R) -> R {
+        self.parser.reset();
+        self.done = false;
+        self.total_bytes = None;
+        std::mem::replace(&mut self.reader, reader)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub async fn next_message(&mut self) -> Option<Result<Message, UlogError>> {
+        poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncUlogReader<R> {
+    type Item = Result<Message, UlogError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(result) = this.parser.next_message() {
+                this.done |= result.is_err();
+                return Poll::Ready(Some(result));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let mut buf = ReadBuf::new(&mut this.chunk);
+            match Pin::new(&mut this.reader).poll_read(cx, &mut buf) {
+                Poll::Pending => return Poll::Pending,
+                // Messages may still follow a resync past the leftover bytes.
+                Poll::Ready(Ok(())) if buf.filled().is_empty() => {
+                    this.done = true;
+                    if let Err(err) = this.parser.finish() {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Poll::Ready(Ok(())) => {
+                    let read = buf.filled().len();
+                    let _ = this.parser.feed(&this.chunk[..read]);
+                }
+                Poll::Ready(Err(err)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(UlogError {
+                        kind: UlogErrorKind::Io(err),
+                        offset: this.parser.offset() + this.parser.buffered(),
+                        message_index: None,
+                    })));
+                }
+            }
+        }
+    }
+}
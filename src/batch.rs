@@ -0,0 +1,67 @@
+use std::{
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+
+use crate::{parse_ulog_file_with_options, ParseOptions, Ulog, UlogError};
+
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub error: UlogError,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchResult<T> {
+    pub results: Vec<(PathBuf, T)>,
+    pub errors: Vec<FileError>,
+}
+
+impl<T> BatchResult<T> {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+pub fn parse_many<P, T, F>(paths: &[P], options: &ParseOptions, callback: F) -> BatchResult<T>
+where
+    P: AsRef<Path> + Sync,
+    T: Send,
+    F: Fn(&Path, Ulog) -> T + Sync,
+{
+    let outcomes: Vec<_> = paths
+        .par_iter()
+        .map(|path| {
+            let path = path.as_ref();
+            let outcome =
+                parse_ulog_file_with_options(path, options).map(|ulog| callback(path, ulog));
+            (path.to_path_buf(), outcome)
+        })
+        .collect();
+    let mut batch = BatchResult {
+        results: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (path, outcome) in outcomes {
+        match outcome {
+            Ok(result) => batch.results.push((path, result)),
+            Err(error) => batch.errors.push(FileError { path, error }),
+        }
+    }
+    batch
+}
@@ -0,0 +1,467 @@
+use std::{borrow::Cow, str};
+
+use bytes::Bytes;
+use nom::{
+    bytes::complete::take,
+    combinator::rest,
+    number::complete::{le_u16, le_u64, u8},
+};
+
+use crate::{
+    header, message_flag_bits, message_frame, too_short, utf8_string, DefaultTypes, Header,
+    Message, MessageAddLogged, MessageData, MessageDropout, MessageFlagBits, MessageFormat,
+    MessageHeader, MessageInfo, MessageInfoMultiple, MessageLogging, MessageLoggingTagged,
+    MessageParameter, MessageParameterDefault, MessageRaw, MessageRemoveLogged, MessageSync,
+    MsgType, ParseError, ParseOptions, ParseResult, UlogError, UlogErrorKind, Utf8Policy, MAGIC,
+};
+
+#[derive(Debug, Clone)]
+pub enum BorrowedMessage<'a> {
+    Format {
+        header: MessageHeader,
+        format: Cow<'a, str>,
+    },
+    Info {
+        header: MessageHeader,
+        key_len: u8,
+        key: Cow<'a, str>,
+        value: &'a [u8],
+    },
+    InfoMultiple {
+        header: MessageHeader,
+        is_continued: u8,
+        key_len: u8,
+        key: Cow<'a, str>,
+        value: &'a [u8],
+    },
+    Parameter {
+        header: MessageHeader,
+        key_len: u8,
+        key: Cow<'a, str>,
+        value: &'a [u8],
+    },
+    ParameterDefault {
+        header: MessageHeader,
+        default_types: DefaultTypes,
+        key_len: u8,
+        key: Cow<'a, str>,
+        value: &'a [u8],
+    },
+    AddLogged {
+        header: MessageHeader,
+        multi_id: u8,
+        msg_id: u16,
+        message_name: Cow<'a, str>,
+    },
+    RemoveLogged {
+        header: MessageHeader,
+        msg_id: u16,
+    },
+    Data {
+        header: MessageHeader,
+        msg_id: u16,
+        data: &'a [u8],
+    },
+    Logging {
+        header: MessageHeader,
+        log_level: u8,
+        timestamp: u64,
+        message: Cow<'a, str>,
+    },
+    LoggingTagged {
+        header: MessageHeader,
+        log_level: u8,
+        tag: u16,
+        timestamp: u64,
+        message: Cow<'a, str>,
+    },
+    Sync {
+        header: MessageHeader,
+        sync_magic: [u8; 8],
+    },
+    Dropout {
+        header: MessageHeader,
+        duration: u16,
+    },
+    Raw {
+        header: MessageHeader,
+        data: &'a [u8],
+    },
+}
+
+impl BorrowedMessage<'_> {
+    pub fn header(&self) -> MessageHeader {
+        match *self {
+            BorrowedMessage::Format { header, .. }
+            | BorrowedMessage::Info { header, .. }
+            | BorrowedMessage::InfoMultiple { header, .. }
+            | BorrowedMessage::Parameter { header, .. }
+            | BorrowedMessage::ParameterDefault { header, .. }
+            | BorrowedMessage::AddLogged { header, .. }
+            | BorrowedMessage::RemoveLogged { header, .. }
+            | BorrowedMessage::Data { header, .. }
+            | BorrowedMessage::Logging { header, .. }
+            | BorrowedMessage::LoggingTagged { header, .. }
+            | BorrowedMessage::Sync { header, .. }
+            | BorrowedMessage::Dropout { header, .. }
+            | BorrowedMessage::Raw { header, .. } => header,
+        }
+    }
+
+    pub fn into_owned(self) -> Message {
+        match self {
+            BorrowedMessage::Format { header, format } => Message::Format(MessageFormat {
+                header,
+                format: format.into_owned(),
+            }),
+            BorrowedMessage::Info {
+                header,
+                key_len,
+                key,
+                value,
+            } => Message::Info(MessageInfo {
+                header,
+                key_len,
+                key: key.into(),
+                value: value.to_vec(),
+            }),
+            BorrowedMessage::InfoMultiple {
+                header,
+                is_continued,
+                key_len,
+                key,
+                value,
+            } => Message::InfoMultiple(MessageInfoMultiple {
+                header,
+                is_continued,
+                key_len,
+                key: key.into(),
+                value: value.to_vec(),
+            }),
+            BorrowedMessage::Parameter {
+                header,
+                key_len,
+                key,
+                value,
+            } => Message::Parameter(MessageParameter {
+                header,
+                key_len,
+                key: key.into(),
+                value: value.to_vec(),
+            }),
+            BorrowedMessage::ParameterDefault {
+                header,
+                default_types,
+                key_len,
+                key,
+                value,
+            } => Message::ParameterDefault(MessageParameterDefault {
+                header,
+                default_types,
+                key_len,
+                key: key.into(),
+                value: value.to_vec(),
+            }),
+            BorrowedMessage::AddLogged {
+                header,
+                multi_id,
+                msg_id,
+                message_name,
+            } => Message::AddLogged(MessageAddLogged {
+                header,
+                multi_id,
+                msg_id,
+                message_name: message_name.into(),
+            }),
+            BorrowedMessage::RemoveLogged { header, msg_id } => {
+                Message::RemoveLogged(MessageRemoveLogged { header, msg_id })
+            }
+            BorrowedMessage::Data {
+                header,
+                msg_id,
+                data,
+            } => Message::Data(MessageData {
+                header,
+                msg_id,
+                data: Bytes::copy_from_slice(data),
+            }),
+            BorrowedMessage::Logging {
+                header,
+                log_level,
+                timestamp,
+                message,
+            } => Message::Logging(MessageLogging {
+                header,
+                log_level,
+                timestamp,
+                message: message.into_owned(),
+            }),
+            BorrowedMessage::LoggingTagged {
+                header,
+                log_level,
+                tag,
+                timestamp,
+                message,
+            } => Message::LoggingTagged(MessageLoggingTagged {
+                header,
+                log_level,
+                tag,
+                timestamp,
+                message: message.into_owned(),
+            }),
+            BorrowedMessage::Sync { header, sync_magic } => {
+                Message::Sync(MessageSync { header, sync_magic })
+            }
+            BorrowedMessage::Dropout { header, duration } => {
+                Message::Dropout(MessageDropout { header, duration })
+            }
+            BorrowedMessage::Raw { header, data } => Message::Raw(MessageRaw {
+                header,
+                data: data.to_vec(),
+            }),
+        }
+    }
+}
+
+fn utf8_str<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> Result<Cow<'a, str>, nom::Err<ParseError<'a>>> {
+    match options.utf8_policy {
+        Utf8Policy::Lossy => Ok(String::from_utf8_lossy(input)),
+        Utf8Policy::Raw | Utf8Policy::Error => match str::from_utf8(input) {
+            Ok(string) => Ok(Cow::Borrowed(string)),
+            Err(_) => utf8_string(input, options).map(Cow::Owned),
+        },
+    }
+}
+
+fn key_value<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> ParseResult<'a, (u8, Cow<'a, str>, &'a [u8])> {
+    let (input, key_len) = u8(input)?;
+    let (input, key) = take(key_len)(input)?;
+    let (input, value) = rest(input)?;
+    Ok((input, (key_len, utf8_str(key, options)?, value)))
+}
+
+pub(crate) fn borrowed_fields<'a>(
+    input: &'a [u8],
+    msg_type: MsgType,
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, BorrowedMessage<'a>> {
+    match msg_type {
+        MsgType::Format => {
+            let (input, format) = rest(input)?;
+            let format = utf8_str(format, options)?;
+            Ok((input, BorrowedMessage::Format { header, format }))
+        }
+        MsgType::Info => {
+            let (input, (key_len, key, value)) = key_value(input, options)?;
+            let message = BorrowedMessage::Info {
+                header,
+                key_len,
+                key,
+                value,
+            };
+            Ok((input, message))
+        }
+        MsgType::InfoMultiple => {
+            let (input, is_continued) = u8(input)?;
+            let (input, (key_len, key, value)) = key_value(input, options)?;
+            let message = BorrowedMessage::InfoMultiple {
+                header,
+                is_continued,
+                key_len,
+                key,
+                value,
+            };
+            Ok((input, message))
+        }
+        MsgType::Parameter => {
+            let (input, (key_len, key, value)) = key_value(input, options)?;
+            let message = BorrowedMessage::Parameter {
+                header,
+                key_len,
+                key,
+                value,
+            };
+            Ok((input, message))
+        }
+        MsgType::ParameterDefault => {
+            let (input, default_types) = u8(input)?;
+            let (input, (key_len, key, value)) = key_value(input, options)?;
+            let message = BorrowedMessage::ParameterDefault {
+                header,
+                default_types: DefaultTypes::from_bits_retain(default_types),
+                key_len,
+                key,
+                value,
+            };
+            Ok((input, message))
+        }
+        MsgType::AddLogged => {
+            let (input, multi_id) = u8(input)?;
+            let (input, msg_id) = le_u16(input)?;
+            let (input, message_name) = rest(input)?;
+            let message = BorrowedMessage::AddLogged {
+                header,
+                multi_id,
+                msg_id,
+                message_name: utf8_str(message_name, options)?,
+            };
+            Ok((input, message))
+        }
+        MsgType::RemoveLogged => {
+            let (input, msg_id) = le_u16(input)?;
+            Ok((input, BorrowedMessage::RemoveLogged { header, msg_id }))
+        }
+        MsgType::Data => {
+            let (input, msg_id) = le_u16(input)?;
+            let (input, data) = rest(input)?;
+            let message = BorrowedMessage::Data {
+                header,
+                msg_id,
+                data,
+            };
+            Ok((input, message))
+        }
+        MsgType::Logging => {
+            let (input, log_level) = u8(input)?;
+            let (input, timestamp) = le_u64(input)?;
+            let (input, message) = rest(input)?;
+            let message = BorrowedMessage::Logging {
+                header,
+                log_level,
+                timestamp,
+                message: utf8_str(message, options)?,
+            };
+            Ok((input, message))
+        }
+        MsgType::LoggingTagged => {
+            let (input, log_level) = u8(input)?;
+            let (input, tag) = le_u16(input)?;
+            let (input, timestamp) = le_u64(input)?;
+            let (input, message) = rest(input)?;
+            let message = BorrowedMessage::LoggingTagged {
+                header,
+                log_level,
+                tag,
+                timestamp,
+                message: utf8_str(message, options)?,
+            };
+            Ok((input, message))
+        }
+        MsgType::Sync => {
+            let (input, sync_magic) = take(8usize)(input)?;
+            let message = BorrowedMessage::Sync {
+                header,
+                sync_magic: sync_magic.try_into().unwrap(),
+            };
+            Ok((input, message))
+        }
+        MsgType::Dropout => {
+            let (input, duration) = le_u16(input)?;
+            Ok((input, BorrowedMessage::Dropout { header, duration }))
+        }
+        msg_type => Err(nom::Err::Error(ParseError::new(
+            input,
+            UlogErrorKind::UnknownMessageType(msg_type.into()),
+        ))),
+    }
+}
+
+pub fn borrowed_message_body<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, BorrowedMessage<'a>> {
+    match borrowed_fields(input, header.msg_type, header, options) {
+        Err(nom::Err::Failure(ParseError {
+            kind: UlogErrorKind::InvalidUtf8(_),
+            ..
+        })) if options.utf8_policy == Utf8Policy::Raw => Ok((
+            &input[input.len()..],
+            BorrowedMessage::Raw {
+                header,
+                data: input,
+            },
+        )),
+        result => result.map_err(|err| too_short(err, header)),
+    }
+}
+
+pub fn borrowed_message<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> ParseResult<'a, BorrowedMessage<'a>> {
+    let (input, (header, body)) = message_frame(input)?;
+    let (_, message) = borrowed_message_body(body, header, options)?;
+    Ok((input, message))
+}
+
+#[derive(Debug)]
+pub struct BorrowedMessages<'a> {
+    start: &'a [u8],
+    input: &'a [u8],
+    options: ParseOptions,
+    message_index: usize,
+    pub header: Header,
+    pub message_flag_bits: MessageFlagBits,
+}
+
+impl<'a> BorrowedMessages<'a> {
+    pub fn new(input: &'a [u8], options: &ParseOptions) -> Result<Self, UlogError> {
+        let (rest, header) = header(input).map_err(|err| UlogError::new(err, input))?;
+        let (rest, message_flag_bits) =
+            message_flag_bits(rest, options).map_err(|err| UlogError::new(err, input))?;
+        Ok(BorrowedMessages {
+            start: input,
+            input: rest,
+            options: options.clone(),
+            message_index: 0,
+            header,
+            message_flag_bits,
+        })
+    }
+
+    pub fn rest(&self) -> &'a [u8] {
+        self.input
+    }
+}
+
+impl<'a> Iterator for BorrowedMessages<'a> {
+    type Item = Result<BorrowedMessage<'a>, UlogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.input.is_empty() && !self.input.starts_with(&MAGIC) {
+            let result = message_frame(self.input).and_then(|(rest, (header, body))| {
+                let message = match borrowed_message_body(body, header, &self.options) {
+                    Err(nom::Err::Error(ParseError {
+                        kind: UlogErrorKind::UnknownMessageType(_),
+                        ..
+                    })) => None,
+                    result => Some(result?.1),
+                };
+                Ok((rest, message))
+            });
+            match result {
+                Ok((rest, message)) => {
+                    self.input = rest;
+                    self.message_index += 1;
+                    if let Some(message) = message {
+                        return Some(Ok(message));
+                    }
+                }
+                Err(err) => {
+                    let err = err.map(|err| err.at_message(self.message_index));
+                    self.input = &self.input[self.input.len()..];
+                    return Some(Err(UlogError::new(err, self.start)));
+                }
+            }
+        }
+        None
+    }
+}
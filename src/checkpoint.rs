@@ -0,0 +1,148 @@
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+use crate::{
+    FormatDefinition, FormatError, Header, MessageAddLogged, MessageFlagBits, MessageHeader,
+    MsgType, ParseOptions, UlogParser, UlogReader,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CheckpointFlagBits {
+    pub msg_size: u16,
+    pub compat_flags: [u8; 8],
+    pub incompat_flags: [u8; 8],
+    pub appended_offsets: [u64; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CheckpointSubscription {
+    pub msg_id: u16,
+    pub multi_id: u8,
+    pub message_name: String,
+    pub added: usize,
+    pub removed: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    pub offset: usize,
+    pub message_index: usize,
+    pub version: u8,
+    pub timestamp: u64,
+    pub flag_bits: Option<CheckpointFlagBits>,
+    pub formats: Vec<String>,
+    pub subscriptions: Vec<CheckpointSubscription>,
+}
+
+impl UlogParser {
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        let header = self.header.as_ref()?;
+        let mut formats: Vec<String> = self
+            .decoder
+            .formats
+            .definitions
+            .values()
+            .map(FormatDefinition::to_string)
+            .collect();
+        formats.sort();
+        Some(Checkpoint {
+            offset: self.offset(),
+            message_index: self.message_count(),
+            version: header.version,
+            timestamp: header.timestamp,
+            flag_bits: self
+                .message_flag_bits
+                .as_ref()
+                .map(|flag_bits| CheckpointFlagBits {
+                    msg_size: flag_bits.header.msg_size,
+                    compat_flags: flag_bits.compat_flags,
+                    incompat_flags: flag_bits.incompat_flags,
+                    appended_offsets: flag_bits.appended_offsets,
+                }),
+            formats,
+            subscriptions: self
+                .subscriptions
+                .subscriptions
+                .iter()
+                .map(|subscription| CheckpointSubscription {
+                    msg_id: subscription.msg_id,
+                    multi_id: subscription.multi_id,
+                    message_name: subscription.message_name.to_string(),
+                    added: subscription.added,
+                    removed: subscription.removed,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn resume(checkpoint: &Checkpoint, options: ParseOptions) -> Result<Self, FormatError> {
+        let mut parser = UlogParser::with_options(options);
+        parser.resume_at(checkpoint.offset, checkpoint.message_index);
+        parser.header = Some(Header {
+            version: checkpoint.version,
+            timestamp: checkpoint.timestamp,
+        });
+        parser.message_flag_bits = checkpoint
+            .flag_bits
+            .as_ref()
+            .map(|flag_bits| MessageFlagBits {
+                header: MessageHeader {
+                    msg_size: flag_bits.msg_size,
+                    msg_type: MsgType::FlagBits,
+                },
+                compat_flags: flag_bits.compat_flags,
+                incompat_flags: flag_bits.incompat_flags,
+                appended_offsets: flag_bits.appended_offsets,
+            });
+        parser.resume_appended();
+        for format in &checkpoint.formats {
+            let definition =
+                FormatDefinition::parse(format).map_err(|error| FormatError::Invalid {
+                    format: format.clone(),
+                    error,
+                })?;
+            parser.decoder.formats.insert(definition);
+        }
+        for subscription in &checkpoint.subscriptions {
+            let add_logged = MessageAddLogged {
+                header: MessageHeader {
+                    msg_size: (3 + subscription.message_name.len()) as u16,
+                    msg_type: MsgType::AddLogged,
+                },
+                multi_id: subscription.multi_id,
+                msg_id: subscription.msg_id,
+                message_name: Arc::from(subscription.message_name.as_str()),
+            };
+            parser
+                .subscriptions
+                .add_logged(&add_logged, subscription.added);
+            match subscription.removed {
+                Some(removed) => parser
+                    .subscriptions
+                    .remove_logged(subscription.msg_id, removed),
+                None => parser.decoder.add_logged(&add_logged)?,
+            }
+        }
+        Ok(parser)
+    }
+}
+
+impl<R: Read + Seek> UlogReader<R> {
+    pub fn resume(
+        mut reader: R,
+        checkpoint: &Checkpoint,
+        options: ParseOptions,
+    ) -> io::Result<Self> {
+        let parser = UlogParser::resume(checkpoint, options)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        reader.seek(SeekFrom::Start(checkpoint.offset as u64))?;
+        let mut reader = UlogReader::new(reader);
+        reader.parser = parser;
+        Ok(reader)
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    io::{self, Read},
+    sync::Arc,
+};
+
+use crate::{
+    Dataset, DecodeError, Layout, Message, OrphanPolicy, ParseMode, UlogError, UlogReader,
+};
+
+pub trait DatasetSink {
+    fn write_dataset(&mut self, dataset: Dataset) -> io::Result<()>;
+}
+
+impl<F: FnMut(Dataset) -> io::Result<()>> DatasetSink for F {
+    fn write_dataset(&mut self, dataset: Dataset) -> io::Result<()> {
+        self(dataset)
+    }
+}
+
+#[derive(Debug)]
+pub enum ChunkedError {
+    Parse(UlogError),
+    Decode(DecodeError),
+    Sink(io::Error),
+}
+
+impl fmt::Display for ChunkedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkedError::Parse(err) => write!(f, "{}", err),
+            ChunkedError::Decode(err) => write!(f, "{}", err),
+            ChunkedError::Sink(err) => write!(f, "cannot write dataset: {}", err),
+        }
+    }
+}
+
+impl Error for ChunkedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChunkedError::Parse(err) => Some(err),
+            ChunkedError::Decode(err) => Some(err),
+            ChunkedError::Sink(err) => Some(err),
+        }
+    }
+}
+
+impl From<UlogError> for ChunkedError {
+    fn from(err: UlogError) -> Self {
+        ChunkedError::Parse(err)
+    }
+}
+
+impl From<DecodeError> for ChunkedError {
+    fn from(err: DecodeError) -> Self {
+        ChunkedError::Decode(err)
+    }
+}
+
+// Data left out of the datasets, either because no AddLogged subscribed its msg_id or
+// because it could not be decoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkippedData {
+    pub orphans: usize,
+    pub undecodable: usize,
+}
+
+struct Buffered {
+    layout: Arc<Layout>,
+    dataset: Dataset,
+}
+
+impl<R: Read> UlogReader<R> {
+    pub fn write_datasets(
+        mut self,
+        max_buffered_bytes: usize,
+        sink: &mut impl DatasetSink,
+    ) -> Result<SkippedData, ChunkedError> {
+        let strict = self.parser.options().mode == ParseMode::Strict;
+        let orphan_error = strict || self.parser.options().orphan_policy == OrphanPolicy::Error;
+        let mut skipped = SkippedData::default();
+        let mut buffered: HashMap<(String, u8), Buffered> = HashMap::new();
+        let mut buffered_bytes = 0;
+        while let Some(message) = self.next() {
+            let Message::Data(data) = message? else {
+                continue;
+            };
+            if self.parser.subscriptions.get(data.msg_id).is_none() {
+                if orphan_error {
+                    return Err(DecodeError::UnknownMsgId(data.msg_id).into());
+                }
+                skipped.orphans += 1;
+                continue;
+            }
+            let decoded = match self.parser.decoder.decode(&data) {
+                Ok(decoded) => decoded,
+                Err(err) if strict => return Err(err.into()),
+                Err(_err) => {
+                    debug!("skipping data for msg_id {}: {}", data.msg_id, _err);
+                    skipped.undecodable += 1;
+                    continue;
+                }
+            };
+            let entry = buffered
+                .entry((decoded.layout.name.clone(), decoded.multi_id))
+                .or_insert_with(|| Buffered {
+                    dataset: Dataset::new(&decoded.layout, decoded.multi_id),
+                    layout: decoded.layout.clone(),
+                });
+            entry.dataset.push(decoded.values);
+            buffered_bytes += entry.layout.size;
+            if buffered_bytes >= max_buffered_bytes {
+                flush(&mut buffered, sink)?;
+                buffered_bytes = 0;
+            }
+        }
+        flush(&mut buffered, sink)?;
+        Ok(skipped)
+    }
+}
+
+fn flush(
+    buffered: &mut HashMap<(String, u8), Buffered>,
+    sink: &mut impl DatasetSink,
+) -> Result<(), ChunkedError> {
+    let mut keys: Vec<_> = buffered
+        .iter()
+        .filter(|(_, buffered)| !buffered.dataset.is_empty())
+        .map(|(key, _)| key.clone())
+        .collect();
+    keys.sort();
+    for key in keys {
+        let entry = buffered.get_mut(&key).unwrap();
+        let dataset = std::mem::replace(&mut entry.dataset, Dataset::new(&entry.layout, key.1));
+        sink.write_dataset(dataset).map_err(ChunkedError::Sink)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use bytes::Bytes;
+
+use crate::{
+    parse_ulog_bytes, ParseOptions, Ulog, UlogError, UlogErrorKind, UlogStreamWriter, UlogWriter,
+    WriteError,
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    pub fn detect(input: &[u8]) -> Self {
+        if input.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if input.starts_with(&XZ_MAGIC) {
+            Compression::Xz
+        } else if input.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("gz") => Compression::Gzip,
+            Some("xz") => Compression::Xz,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Xz => "xz",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    fn unsupported(&self) -> io::Error {
+        io::Error::new(
+            ErrorKind::Unsupported,
+            format!("{0} compressed input needs the `{0}` feature", self.name()),
+        )
+    }
+}
+
+// Picks the decompressor from the first bytes of the stream, which are not consumed.
+pub fn decompressing_reader<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn Read + 'a>> {
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+        #[allow(unreachable_patterns)]
+        compression => return Err(compression.unsupported()),
+    })
+}
+
+pub fn decompress(input: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    if Compression::detect(input) == Compression::None {
+        return Ok(Cow::Borrowed(input));
+    }
+    let mut output = Vec::new();
+    decompressing_reader(input)?.read_to_end(&mut output)?;
+    Ok(Cow::Owned(output))
+}
+
+pub fn read_ulog_file(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let input = fs::read(path)?;
+    Ok(match decompress(&input)? {
+        Cow::Borrowed(_) => input,
+        Cow::Owned(output) => output,
+    })
+}
+
+pub fn parse_ulog_file(path: impl AsRef<Path>) -> Result<Ulog, UlogError> {
+    parse_ulog_file_with_options(path, &ParseOptions::default())
+}
+
+pub fn parse_ulog_file_with_options(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> Result<Ulog, UlogError> {
+    let input = read_ulog_file(path).map_err(|err| UlogError {
+        kind: UlogErrorKind::Io(err),
+        offset: 0,
+        message_index: None,
+    })?;
+    parse_ulog_bytes(Bytes::from(input), options)
+}
+
+// Compresses everything written to it; `finish` must be called to complete the stream.
+pub enum CompressedWriter<W: Write> {
+    None(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "xz")]
+    Xz(xz2::write::XzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn new(writer: W, compression: Compression) -> io::Result<Self> {
+        Ok(match compression {
+            Compression::None => CompressedWriter::None(writer),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => CompressedWriter::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "xz")]
+            Compression::Xz => CompressedWriter::Xz(xz2::write::XzEncoder::new(writer, 6)),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => CompressedWriter::Zstd(zstd::Encoder::new(writer, 0)?),
+            #[allow(unreachable_patterns)]
+            compression => return Err(compression.unsupported()),
+        })
+    }
+
+    pub fn compression(&self) -> Compression {
+        match self {
+            CompressedWriter::None(_) => Compression::None,
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "xz")]
+            CompressedWriter::Xz(_) => Compression::Xz,
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    // Without any compression feature only `None` is left.
+    #[allow(clippy::infallible_destructuring_match)]
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            CompressedWriter::None(writer) => writer,
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "xz")]
+            CompressedWriter::Xz(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+// The encoders do not all implement Debug.
+impl<W: Write> fmt::Debug for CompressedWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedWriter")
+            .field("compression", &self.compression())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::None(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "xz")]
+            CompressedWriter::Xz(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::None(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "xz")]
+            CompressedWriter::Xz(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+pub fn create_ulog_file(path: impl AsRef<Path>) -> io::Result<CompressedWriter<BufWriter<File>>> {
+    let compression = Compression::from_path(&path);
+    CompressedWriter::new(BufWriter::new(File::create(path)?), compression)
+}
+
+impl UlogWriter<CompressedWriter<BufWriter<File>>> {
+    // Compresses the log according to the file extension; the stream is only complete
+    // once the writer returned by `finish` is finished as well.
+    pub fn create(path: impl AsRef<Path>, timestamp: u64) -> io::Result<Self> {
+        UlogWriter::new(create_ulog_file(path)?, timestamp)
+    }
+}
+
+impl UlogStreamWriter<CompressedWriter<BufWriter<File>>> {
+    pub fn create(path: impl AsRef<Path>, timestamp: u64) -> io::Result<Self> {
+        UlogStreamWriter::new(create_ulog_file(path)?, timestamp)
+    }
+}
+
+impl Ulog {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), WriteError> {
+        let mut writer = create_ulog_file(path)?;
+        self.write_to(&mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::{
+    DefaultTypes, FormatDefinition, MessageAddLogged, MessageData, MessageDropout, MessageFormat,
+    MessageHeader, MessageInfo, MessageInfoMultiple, MessageLogging, MessageLoggingTagged,
+    MessageParameter, MessageParameterDefault, MessageRemoveLogged, MessageSync, MsgType,
+    ParameterValue, UlogValue, WriteError, SYNC_MAGIC,
+};
+
+impl MessageHeader {
+    pub fn new(msg_type: MsgType, msg_size: usize) -> Result<Self, WriteError> {
+        let msg_size = u16::try_from(msg_size).map_err(|_| WriteError::MessageTooLarge {
+            msg_type,
+            size: msg_size,
+        })?;
+        Ok(MessageHeader { msg_size, msg_type })
+    }
+}
+
+pub(crate) fn key_len(key: &str) -> Result<u8, WriteError> {
+    u8::try_from(key.len()).map_err(|_| WriteError::KeyTooLong(key.to_string()))
+}
+
+pub(crate) fn typed_key(name: &str, value: &UlogValue) -> String {
+    let field_type = value.field_type();
+    match value {
+        UlogValue::String(v) => format!("{}[{}] {}", field_type.name(), v.len(), name),
+        value => match value.array_len() {
+            Some(len) => format!("{}[{}] {}", field_type.name(), len, name),
+            None => format!("{} {}", field_type.name(), name),
+        },
+    }
+}
+
+fn encoded(value: &UlogValue) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.encode(&mut bytes);
+    bytes
+}
+
+pub(crate) fn parameter_key(name: &str, value: ParameterValue) -> (String, Vec<u8>) {
+    match value {
+        ParameterValue::Float(v) => (format!("float {}", name), v.to_le_bytes().to_vec()),
+        ParameterValue::Int32(v) => (format!("int32_t {}", name), v.to_le_bytes().to_vec()),
+    }
+}
+
+impl MessageFormat {
+    pub fn new(definition: &FormatDefinition) -> Result<Self, WriteError> {
+        let format = definition.to_string();
+        Ok(MessageFormat {
+            header: MessageHeader::new(MsgType::Format, format.len())?,
+            format,
+        })
+    }
+}
+
+impl MessageInfo {
+    pub fn new(key: &str, value: Vec<u8>) -> Result<Self, WriteError> {
+        let key_len = key_len(key)?;
+        Ok(MessageInfo {
+            header: MessageHeader::new(MsgType::Info, 1 + key.len() + value.len())?,
+            key_len,
+            key: Arc::from(key),
+            value,
+        })
+    }
+
+    pub fn typed(name: &str, value: &UlogValue) -> Result<Self, WriteError> {
+        Self::new(&typed_key(name, value), encoded(value))
+    }
+}
+
+impl MessageInfoMultiple {
+    pub fn new(key: &str, value: Vec<u8>, is_continued: bool) -> Result<Self, WriteError> {
+        let key_len = key_len(key)?;
+        Ok(MessageInfoMultiple {
+            header: MessageHeader::new(MsgType::InfoMultiple, 2 + key.len() + value.len())?,
+            is_continued: u8::from(is_continued),
+            key_len,
+            key: Arc::from(key),
+            value,
+        })
+    }
+
+    pub fn typed(name: &str, value: &UlogValue, is_continued: bool) -> Result<Self, WriteError> {
+        Self::new(&typed_key(name, value), encoded(value), is_continued)
+    }
+}
+
+impl MessageParameter {
+    pub fn new(name: &str, value: ParameterValue) -> Result<Self, WriteError> {
+        let (key, value) = parameter_key(name, value);
+        let key_len = key_len(&key)?;
+        Ok(MessageParameter {
+            header: MessageHeader::new(MsgType::Parameter, 1 + key.len() + value.len())?,
+            key_len,
+            key: Arc::from(key),
+            value,
+        })
+    }
+}
+
+impl MessageParameterDefault {
+    pub fn new(
+        name: &str,
+        value: ParameterValue,
+        default_types: DefaultTypes,
+    ) -> Result<Self, WriteError> {
+        let (key, value) = parameter_key(name, value);
+        let key_len = key_len(&key)?;
+        Ok(MessageParameterDefault {
+            header: MessageHeader::new(MsgType::ParameterDefault, 2 + key.len() + value.len())?,
+            default_types,
+            key_len,
+            key: Arc::from(key),
+            value,
+        })
+    }
+}
+
+impl MessageAddLogged {
+    pub fn new(message_name: &str, multi_id: u8, msg_id: u16) -> Result<Self, WriteError> {
+        Ok(MessageAddLogged {
+            header: MessageHeader::new(MsgType::AddLogged, 3 + message_name.len())?,
+            multi_id,
+            msg_id,
+            message_name: Arc::from(message_name),
+        })
+    }
+}
+
+impl MessageRemoveLogged {
+    pub fn new(msg_id: u16) -> Self {
+        MessageRemoveLogged {
+            header: MessageHeader {
+                msg_size: 2,
+                msg_type: MsgType::RemoveLogged,
+            },
+            msg_id,
+        }
+    }
+}
+
+impl MessageData {
+    pub fn new(msg_id: u16, data: impl Into<Bytes>) -> Result<Self, WriteError> {
+        let data = data.into();
+        Ok(MessageData {
+            header: MessageHeader::new(MsgType::Data, 2 + data.len())?,
+            msg_id,
+            data,
+        })
+    }
+}
+
+impl MessageLogging {
+    pub fn new(log_level: u8, timestamp: u64, message: &str) -> Result<Self, WriteError> {
+        Ok(MessageLogging {
+            header: MessageHeader::new(MsgType::Logging, 9 + message.len())?,
+            log_level,
+            timestamp,
+            message: message.to_string(),
+        })
+    }
+}
+
+impl MessageLoggingTagged {
+    pub fn new(log_level: u8, tag: u16, timestamp: u64, message: &str) -> Result<Self, WriteError> {
+        Ok(MessageLoggingTagged {
+            header: MessageHeader::new(MsgType::LoggingTagged, 11 + message.len())?,
+            log_level,
+            tag,
+            timestamp,
+            message: message.to_string(),
+        })
+    }
+}
+
+impl MessageSync {
+    pub fn new() -> Self {
+        MessageSync {
+            header: MessageHeader {
+                msg_size: 8,
+                msg_type: MsgType::Sync,
+            },
+            sync_magic: SYNC_MAGIC,
+        }
+    }
+}
+
+impl Default for MessageSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageDropout {
+    pub fn new(duration: u16) -> Self {
+        MessageDropout {
+            header: MessageHeader {
+                msg_size: 2,
+                msg_type: MsgType::Dropout,
+            },
+            duration,
+        }
+    }
+}
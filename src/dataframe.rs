@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use polars::prelude::{
+    Column as PolarsColumn, DataFrame, NamedFrom, PolarsResult, ReshapeDimension, Series,
+};
+
+use crate::{Column, ColumnData, Dataset, ExportError, Ulog};
+
+// Array fields become fixed-size array columns and char arrays strings.
+fn series(column: &Column) -> PolarsResult<Series> {
+    let name = column.name.as_str().into();
+    let series = match &column.data {
+        ColumnData::Int8(v) => Series::new(name, v),
+        ColumnData::UInt8(v) => Series::new(name, v),
+        ColumnData::Int16(v) => Series::new(name, v),
+        ColumnData::UInt16(v) => Series::new(name, v),
+        ColumnData::Int32(v) => Series::new(name, v),
+        ColumnData::UInt32(v) => Series::new(name, v),
+        ColumnData::Int64(v) => Series::new(name, v),
+        ColumnData::UInt64(v) => Series::new(name, v),
+        ColumnData::Float(v) => Series::new(name, v),
+        ColumnData::Double(v) => Series::new(name, v),
+        ColumnData::Bool(v) => Series::new(name, v),
+        ColumnData::Char(v) => Series::new(name, v.iter().map(char::to_string).collect::<Vec<_>>()),
+        ColumnData::String(v) => return Ok(Series::new(name, v)),
+    };
+    match column.array_len {
+        Some(len) => {
+            series.reshape_array(&[ReshapeDimension::Infer, ReshapeDimension::new(len as i64)])
+        }
+        None => Ok(series),
+    }
+}
+
+impl Dataset {
+    pub fn to_polars(&self) -> PolarsResult<DataFrame> {
+        let columns = self
+            .columns
+            .iter()
+            .filter(|column| !column.name.starts_with("_padding"))
+            .map(|column| series(column).map(PolarsColumn::from))
+            .collect::<PolarsResult<_>>()?;
+        DataFrame::new(self.len(), columns)
+    }
+}
+
+impl Ulog {
+    pub fn dataframe(&self, message_name: &str, multi_id: u8) -> Result<DataFrame, ExportError> {
+        Ok(self.dataset(message_name, multi_id)?.to_polars()?)
+    }
+
+    // One frame per topic instance, keyed by name and multi_id.
+    pub fn dataframes(&self) -> Result<BTreeMap<(String, u8), DataFrame>, ExportError> {
+        self.topic_instances()
+            .into_iter()
+            .map(|(message_name, multi_id)| {
+                let frame = self.dataframe(&message_name, multi_id)?;
+                Ok(((message_name, multi_id), frame))
+            })
+            .collect()
+    }
+}
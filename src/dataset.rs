@@ -0,0 +1,383 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::{Bound, Range, RangeBounds},
+    rc::Rc,
+};
+
+use crate::{string, DecodeError, FieldType, Layout, Ulog, UlogValue};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnData {
+    Int8(Vec<i8>),
+    UInt8(Vec<u8>),
+    Int16(Vec<i16>),
+    UInt16(Vec<u16>),
+    Int32(Vec<i32>),
+    UInt32(Vec<u32>),
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Bool(Vec<bool>),
+    Char(Vec<char>),
+    String(Vec<String>),
+}
+
+macro_rules! column_data {
+    ($($variant:ident, $array:ident;)*) => {
+        impl ColumnData {
+            pub fn len(&self) -> usize {
+                match self {
+                    $(ColumnData::$variant(v) => v.len(),)*
+                    ColumnData::String(v) => v.len(),
+                }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            pub fn get(&self, index: usize) -> Option<UlogValue> {
+                match self {
+                    $(ColumnData::$variant(v) => v.get(index).cloned().map(UlogValue::$variant),)*
+                    ColumnData::String(v) => v.get(index).cloned().map(UlogValue::String),
+                }
+            }
+
+            fn extend_range(&mut self, other: &ColumnData, range: Range<usize>) {
+                match (self, other) {
+                    $((ColumnData::$variant(v), ColumnData::$variant(other)) => v.extend_from_slice(&other[range]),)*
+                    (ColumnData::String(v), ColumnData::String(other)) => v.extend_from_slice(&other[range]),
+                    _ => {}
+                }
+            }
+
+            fn push(&mut self, value: UlogValue) {
+                match (self, value) {
+                    $(
+                        (ColumnData::$variant(v), UlogValue::$variant(value)) => v.push(value),
+                        (ColumnData::$variant(v), UlogValue::$array(values)) => v.extend(values),
+                    )*
+                    (ColumnData::String(v), UlogValue::String(value)) => v.push(value),
+                    (ColumnData::String(v), UlogValue::CharArray(value)) => {
+                        v.push(value.into_iter().collect())
+                    }
+                    _ => {}
+                }
+            }
+        }
+    };
+}
+
+column_data! {
+    Int8, Int8Array;
+    UInt8, UInt8Array;
+    Int16, Int16Array;
+    UInt16, UInt16Array;
+    Int32, Int32Array;
+    UInt32, UInt32Array;
+    Int64, Int64Array;
+    UInt64, UInt64Array;
+    Float, FloatArray;
+    Double, DoubleArray;
+    Bool, BoolArray;
+    Char, CharArray;
+}
+
+impl ColumnData {
+    pub fn new(field_type: &FieldType, array_len: Option<usize>) -> Self {
+        match field_type {
+            FieldType::Int8 => ColumnData::Int8(Vec::new()),
+            FieldType::UInt8 => ColumnData::UInt8(Vec::new()),
+            FieldType::Int16 => ColumnData::Int16(Vec::new()),
+            FieldType::UInt16 => ColumnData::UInt16(Vec::new()),
+            FieldType::Int32 => ColumnData::Int32(Vec::new()),
+            FieldType::UInt32 => ColumnData::UInt32(Vec::new()),
+            FieldType::Int64 => ColumnData::Int64(Vec::new()),
+            FieldType::UInt64 => ColumnData::UInt64(Vec::new()),
+            FieldType::Float => ColumnData::Float(Vec::new()),
+            FieldType::Double => ColumnData::Double(Vec::new()),
+            FieldType::Bool => ColumnData::Bool(Vec::new()),
+            FieldType::Char if array_len.is_some() => ColumnData::String(Vec::new()),
+            FieldType::Char | FieldType::Nested(_) => ColumnData::Char(Vec::new()),
+        }
+    }
+
+    // Reads values straight from the little-endian payload instead of going through UlogValue.
+    fn extend_from_payload(&mut self, bytes: &[u8], array_len: Option<usize>) {
+        macro_rules! extend_le {
+            ($v:expr, $type:ty) => {
+                $v.extend(
+                    bytes
+                        .chunks_exact(std::mem::size_of::<$type>())
+                        .map(|bytes| <$type>::from_le_bytes(bytes.try_into().unwrap())),
+                )
+            };
+        }
+        match self {
+            ColumnData::Int8(v) => extend_le!(v, i8),
+            ColumnData::UInt8(v) => v.extend_from_slice(bytes),
+            ColumnData::Int16(v) => extend_le!(v, i16),
+            ColumnData::UInt16(v) => extend_le!(v, u16),
+            ColumnData::Int32(v) => extend_le!(v, i32),
+            ColumnData::UInt32(v) => extend_le!(v, u32),
+            ColumnData::Int64(v) => extend_le!(v, i64),
+            ColumnData::UInt64(v) => extend_le!(v, u64),
+            ColumnData::Float(v) => extend_le!(v, f32),
+            ColumnData::Double(v) => extend_le!(v, f64),
+            ColumnData::Bool(v) => v.extend(bytes.iter().map(|&b| b != 0)),
+            ColumnData::Char(v) => v.extend(bytes.iter().map(|&c| c as char)),
+            ColumnData::String(_) => {
+                if let Ok((_, value)) = string(bytes, array_len.unwrap_or(bytes.len())) {
+                    self.push(value);
+                }
+            }
+        }
+    }
+
+    pub fn to_f64_vec(&self) -> Option<Vec<f64>> {
+        (0..self.len())
+            .map(|index| self.get(index)?.as_f64())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub field_type: FieldType,
+    pub array_len: Option<usize>,
+    pub data: ColumnData,
+}
+
+impl Column {
+    pub fn stride(&self) -> usize {
+        match (&self.field_type, self.array_len) {
+            (FieldType::Char, Some(_)) => 1,
+            (_, array_len) => array_len.unwrap_or(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len() / self.stride().max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub name: String,
+    pub multi_id: u8,
+    pub timestamps: Vec<u64>,
+    pub columns: Vec<Column>,
+}
+
+impl Dataset {
+    pub fn new(layout: &Layout, multi_id: u8) -> Self {
+        Dataset {
+            name: layout.name.clone(),
+            multi_id,
+            timestamps: Vec::new(),
+            columns: layout
+                .fields
+                .iter()
+                .map(|field| Column {
+                    name: field.name.clone(),
+                    field_type: field.field_type.clone(),
+                    array_len: field.array_len,
+                    data: ColumnData::new(&field.field_type, field.array_len),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn push(&mut self, values: Vec<UlogValue>) {
+        for (column, value) in self.columns.iter_mut().zip(values) {
+            if column.name == "timestamp" {
+                if let Some(timestamp) = value.as_u64() {
+                    self.timestamps.push(timestamp);
+                }
+            }
+            column.data.push(value);
+        }
+    }
+
+    pub fn push_payload(&mut self, layout: &Layout, payload: &[u8]) {
+        for (column, field) in self.columns.iter_mut().zip(&layout.fields) {
+            let Some(bytes) = payload.get(field.offset..field.offset + field.size()) else {
+                continue;
+            };
+            column.data.extend_from_payload(bytes, field.array_len);
+            if column.name == "timestamp" {
+                if let Some(timestamp) = column
+                    .data
+                    .get(column.data.len() - 1)
+                    .and_then(|value| value.as_u64())
+                {
+                    self.timestamps.push(timestamp);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, Column::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Rows are merged by timestamp, with ties in instance order, so the stacked
+    // timestamps stay sorted for `index_at` and `range`.
+    pub fn stack(datasets: &[Dataset]) -> Option<Dataset> {
+        let (first, rest) = datasets.split_first()?;
+        let signature = |dataset: &Dataset| {
+            dataset
+                .columns
+                .iter()
+                .map(|column| {
+                    (
+                        column.name.clone(),
+                        column.field_type.clone(),
+                        column.array_len,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let columns = signature(first);
+        if rest
+            .iter()
+            .any(|dataset| dataset.name != first.name || signature(dataset) != columns)
+        {
+            return None;
+        }
+
+        let mut rows: Vec<(u64, usize, usize)> = datasets
+            .iter()
+            .enumerate()
+            .flat_map(|(index, dataset)| {
+                (0..dataset.len()).map(move |row| {
+                    let timestamp = dataset.timestamps.get(row).copied().unwrap_or(0);
+                    (timestamp, index, row)
+                })
+            })
+            .collect();
+        rows.sort_by_key(|&(timestamp, _, _)| timestamp);
+
+        // Consecutive rows of one instance are copied as a single range.
+        let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+        for (_, index, row) in rows {
+            match runs.last_mut() {
+                Some((last, range)) if *last == index && range.end == row => range.end += 1,
+                _ => runs.push((index, row..row + 1)),
+            }
+        }
+
+        let mut stacked = Dataset {
+            name: first.name.clone(),
+            multi_id: first.multi_id,
+            timestamps: Vec::new(),
+            columns: first
+                .columns
+                .iter()
+                .map(|column| Column {
+                    data: ColumnData::new(&column.field_type, column.array_len),
+                    ..column.clone()
+                })
+                .collect(),
+        };
+        let mut instances = Vec::new();
+        for (index, range) in runs {
+            let dataset = &datasets[index];
+            if let Some(timestamps) = dataset.timestamps.get(range.clone()) {
+                stacked.timestamps.extend_from_slice(timestamps);
+            }
+            for (column, other) in stacked.columns.iter_mut().zip(&dataset.columns) {
+                let stride = other.stride();
+                column
+                    .data
+                    .extend_range(&other.data, range.start * stride..range.end * stride);
+            }
+            instances.extend(std::iter::repeat_n(dataset.multi_id, range.len()));
+        }
+        stacked.columns.push(Column {
+            name: "multi_id".to_string(),
+            field_type: FieldType::UInt8,
+            array_len: None,
+            data: ColumnData::UInt8(instances),
+        });
+        Some(stacked)
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn extend(&mut self, other: &Dataset) {
+        self.timestamps.extend_from_slice(&other.timestamps);
+        for (column, other) in self.columns.iter_mut().zip(&other.columns) {
+            column.data.extend_range(&other.data, 0..other.data.len());
+        }
+    }
+
+    pub fn index_at(&self, timestamp: u64) -> Option<usize> {
+        self.timestamps
+            .partition_point(|&t| t <= timestamp)
+            .checked_sub(1)
+    }
+
+    pub fn range(&self, range: impl RangeBounds<u64>) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => self.timestamps.partition_point(|&t| t < start),
+            Bound::Excluded(&start) => self.timestamps.partition_point(|&t| t <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => self.timestamps.partition_point(|&t| t <= end),
+            Bound::Excluded(&end) => self.timestamps.partition_point(|&t| t < end),
+            Bound::Unbounded => self.timestamps.len(),
+        };
+        start..end.max(start)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+#[derive(Debug)]
+pub struct LazyDatasets<'a> {
+    pub ulog: &'a Ulog,
+    cache: RefCell<HashMap<(String, u8), Rc<Dataset>>>,
+}
+
+impl<'a> LazyDatasets<'a> {
+    pub fn new(ulog: &'a Ulog) -> Self {
+        LazyDatasets {
+            ulog,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, message_name: &str, multi_id: u8) -> Result<Rc<Dataset>, DecodeError> {
+        let key = (message_name.to_string(), multi_id);
+        if let Some(dataset) = self.cache.borrow().get(&key) {
+            return Ok(dataset.clone());
+        }
+        let dataset = Rc::new(self.ulog.dataset(message_name, multi_id)?);
+        self.cache.borrow_mut().insert(key, dataset.clone());
+        Ok(dataset)
+    }
+
+    pub fn is_cached(&self, message_name: &str, multi_id: u8) -> bool {
+        self.cache
+            .borrow()
+            .contains_key(&(message_name.to_string(), multi_id))
+    }
+
+    pub fn clear(&self) {
+        self.cache.borrow_mut().clear();
+    }
+}
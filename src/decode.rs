@@ -0,0 +1,221 @@
+use std::{collections::HashMap, error::Error, fmt, sync::Arc};
+
+use crate::{
+    value, FormatError, Formats, Layout, LayoutField, MessageAddLogged, MessageData, MsgType,
+    UlogError, UlogErrorKind, UlogValue,
+};
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Format(FormatError),
+    UnknownMsgId(u16),
+    UnknownField(String),
+    Data { msg_id: u16, error: UlogError },
+    UnresolvedDataRef(u16),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Format(err) => write!(f, "{}", err),
+            DecodeError::UnknownMsgId(msg_id) => {
+                write!(f, "no subscription for msg_id {}", msg_id)
+            }
+            DecodeError::UnknownField(name) => write!(f, "no field named {:?}", name),
+            DecodeError::Data { msg_id, error } => {
+                write!(f, "cannot decode data for msg_id {}: {}", msg_id, error)
+            }
+            DecodeError::UnresolvedDataRef(msg_id) => write!(
+                f,
+                "data for msg_id {} refers to an input that is not available",
+                msg_id
+            ),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Format(err) => Some(err),
+            DecodeError::Data { error, .. } => Some(error),
+            DecodeError::UnknownMsgId(_)
+            | DecodeError::UnknownField(_)
+            | DecodeError::UnresolvedDataRef(_) => None,
+        }
+    }
+}
+
+impl From<FormatError> for DecodeError {
+    fn from(err: FormatError) -> Self {
+        DecodeError::Format(err)
+    }
+}
+
+pub fn decode_field(data: &MessageData, field: &LayoutField) -> Result<UlogValue, DecodeError> {
+    decode_payload_field(data.msg_id, &data.data, field)
+}
+
+fn decode_payload_field(
+    msg_id: u16,
+    payload: &[u8],
+    field: &LayoutField,
+) -> Result<UlogValue, DecodeError> {
+    let input = payload
+        .get(field.offset..)
+        .unwrap_or(&payload[payload.len()..]);
+    value(input, &field.field_type, field.array_len)
+        .map(|(_, value)| value)
+        .map_err(|err| DecodeError::Data {
+            msg_id,
+            error: UlogError::new(err, payload),
+        })
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedData {
+    pub msg_id: u16,
+    pub multi_id: u8,
+    pub layout: Arc<Layout>,
+    pub values: Vec<UlogValue>,
+}
+
+impl DecodedData {
+    pub fn name(&self) -> &str {
+        &self.layout.name
+    }
+
+    pub fn get(&self, name: &str) -> Option<&UlogValue> {
+        let index = self
+            .layout
+            .fields
+            .iter()
+            .position(|field| field.name == name)?;
+        self.values.get(index)
+    }
+
+    pub fn timestamp(&self) -> Option<u64> {
+        self.get("timestamp")?.as_u64()
+    }
+
+    pub fn element(&self, name: &str, index: usize) -> Option<UlogValue> {
+        self.get(name)?.element(index)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &UlogValue)> {
+        self.layout
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .zip(&self.values)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Decoder {
+    pub formats: Formats,
+    layouts: HashMap<Arc<str>, Arc<Layout>>,
+    subscriptions: HashMap<u16, (u8, Arc<Layout>)>,
+}
+
+impl Decoder {
+    pub fn new(formats: Formats) -> Self {
+        Decoder {
+            formats,
+            ..Self::default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.formats.definitions.clear();
+        self.layouts.clear();
+        self.subscriptions.clear();
+    }
+
+    pub fn add_logged(&mut self, add_logged: &MessageAddLogged) -> Result<(), FormatError> {
+        let layout = match self.layouts.get(&*add_logged.message_name) {
+            Some(layout) => layout.clone(),
+            None => {
+                self.subscriptions.remove(&add_logged.msg_id);
+                let layout = Arc::new(self.formats.layout(&add_logged.message_name)?);
+                self.layouts
+                    .insert(add_logged.message_name.clone(), layout.clone());
+                layout
+            }
+        };
+        self.subscriptions
+            .insert(add_logged.msg_id, (add_logged.multi_id, layout));
+        Ok(())
+    }
+
+    pub fn remove_logged(&mut self, msg_id: u16) {
+        self.subscriptions.remove(&msg_id);
+    }
+
+    pub fn layout(&self, msg_id: u16) -> Option<&Arc<Layout>> {
+        self.subscriptions.get(&msg_id).map(|(_, layout)| layout)
+    }
+
+    pub fn is_subscribed(&self, msg_id: u16) -> bool {
+        self.subscriptions.contains_key(&msg_id)
+    }
+
+    pub fn decode(&self, data: &MessageData) -> Result<DecodedData, DecodeError> {
+        self.decode_payload(data.msg_id, &data.data)
+    }
+
+    pub fn decode_payload(&self, msg_id: u16, payload: &[u8]) -> Result<DecodedData, DecodeError> {
+        let (multi_id, layout) = self
+            .subscriptions
+            .get(&msg_id)
+            .ok_or(DecodeError::UnknownMsgId(msg_id))?;
+        decode_layout(msg_id, *multi_id, layout, payload)
+    }
+
+    pub fn subscription(&self, msg_id: u16) -> Option<(u8, &Arc<Layout>)> {
+        self.subscriptions
+            .get(&msg_id)
+            .map(|(multi_id, layout)| (*multi_id, layout))
+    }
+}
+
+pub(crate) fn check_payload(
+    msg_id: u16,
+    layout: &Layout,
+    payload: &[u8],
+) -> Result<(), DecodeError> {
+    if payload.len() < layout.min_size() {
+        return Err(DecodeError::Data {
+            msg_id,
+            error: UlogError {
+                kind: UlogErrorKind::MessageTooShort {
+                    msg_type: MsgType::Data,
+                    msg_size: u16::try_from(payload.len() + 2).unwrap_or(u16::MAX),
+                },
+                offset: payload.len(),
+                message_index: None,
+            },
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn decode_layout(
+    msg_id: u16,
+    multi_id: u8,
+    layout: &Arc<Layout>,
+    payload: &[u8],
+) -> Result<DecodedData, DecodeError> {
+    check_payload(msg_id, layout, payload)?;
+    let values = layout
+        .fields
+        .iter()
+        .map(|field| decode_payload_field(msg_id, payload, field))
+        .collect::<Result<_, _>>()?;
+    Ok(DecodedData {
+        msg_id,
+        multi_id,
+        layout: layout.clone(),
+        values,
+    })
+}
@@ -0,0 +1,44 @@
+use crate::{Message, MessageInfo, MessageParameter, ParameterValue, Ulog, UlogValue, WriteError};
+
+impl Ulog {
+    // Replaces the info message with this name, whatever its type, or adds one.
+    pub fn set_info(&mut self, name: &str, value: &UlogValue) -> Result<(), WriteError> {
+        let message = Message::Info(MessageInfo::typed(name, value)?);
+        let index = self.definitions.iter().position(
+            |message| matches!(message, Message::Info(info) if info.name() == Some(name)),
+        );
+        match index {
+            Some(index) => self.definitions[index] = message,
+            None => self.definitions.push(message),
+        }
+        Ok(())
+    }
+
+    pub fn remove_info(&mut self, name: &str) -> bool {
+        let len = self.definitions.len();
+        self.definitions
+            .retain(|message| !matches!(message, Message::Info(info) if info.name() == Some(name)));
+        self.definitions.len() != len
+    }
+
+    // Only the initial value changes; later changes in the data section still apply.
+    pub fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), WriteError> {
+        let message = Message::Parameter(MessageParameter::new(name, value)?);
+        let index = self.definitions.iter().position(|message| {
+            matches!(message, Message::Parameter(parameter) if parameter.name() == Some(name))
+        });
+        match index {
+            Some(index) => self.definitions[index] = message,
+            None => self.definitions.push(message),
+        }
+        Ok(())
+    }
+
+    pub fn remove_parameter(&mut self, name: &str) -> bool {
+        let len = self.definitions.len();
+        self.definitions.retain(|message| {
+            !matches!(message, Message::Parameter(parameter) if parameter.name() == Some(name))
+        });
+        self.definitions.len() != len
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read},
+};
+
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher},
+    ChaCha20, ChaCha20Legacy, XChaCha20,
+};
+use rsa::{Oaep, RsaPrivateKey};
+use sha2::Sha256;
+
+pub const KEY_MAGIC: [u8; 7] = *b"ULogKey";
+const KEY_HEADER_SIZE: usize = 7 + 1 + 8 + 1 + 1 + 2 + 2;
+const CRYPTO_RSA_OAEP: u8 = 4;
+
+#[derive(Debug)]
+pub enum DecryptError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedAlgorithm(u8),
+    UnsupportedNonce(usize),
+    Key(rsa::Error),
+    KeyLength(usize),
+}
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecryptError::Io(err) => write!(f, "I/O error: {}", err),
+            DecryptError::InvalidMagic => write!(f, "not an encrypted ULog file"),
+            DecryptError::UnsupportedAlgorithm(algorithm) => {
+                write!(f, "unsupported key exchange algorithm {}", algorithm)
+            }
+            DecryptError::UnsupportedNonce(len) => {
+                write!(f, "unsupported nonce of {} bytes", len)
+            }
+            DecryptError::Key(err) => write!(f, "cannot decrypt the log key: {}", err),
+            DecryptError::KeyLength(len) => {
+                write!(f, "decrypted log key has {} bytes, expected 32", len)
+            }
+        }
+    }
+}
+
+impl Error for DecryptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecryptError::Io(err) => Some(err),
+            DecryptError::Key(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecryptError {
+    fn from(err: io::Error) -> Self {
+        DecryptError::Io(err)
+    }
+}
+
+impl From<rsa::Error> for DecryptError {
+    fn from(err: rsa::Error) -> Self {
+        DecryptError::Key(err)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyHeader {
+    pub version: u8,
+    pub timestamp: u64,
+    pub exchange_algorithm: u8,
+    pub exchange_key: u8,
+    pub key: Vec<u8>,
+    pub nonce: Vec<u8>,
+}
+
+impl KeyHeader {
+    pub fn read(reader: &mut impl Read) -> Result<Self, DecryptError> {
+        let mut header = [0; KEY_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        if header[..7] != KEY_MAGIC {
+            return Err(DecryptError::InvalidMagic);
+        }
+        let key_size = u16::from_le_bytes([header[18], header[19]]);
+        let nonce_size = u16::from_le_bytes([header[20], header[21]]);
+        let mut key = vec![0; key_size.into()];
+        reader.read_exact(&mut key)?;
+        let mut nonce = vec![0; nonce_size.into()];
+        reader.read_exact(&mut nonce)?;
+        Ok(KeyHeader {
+            version: header[7],
+            timestamp: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            exchange_algorithm: header[16],
+            exchange_key: header[17],
+            key,
+            nonce,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        KEY_HEADER_SIZE + self.key.len() + self.nonce.len()
+    }
+}
+
+// The nonce length tells which ChaCha20 variant the log was written with.
+enum Cipher {
+    XChaCha20(XChaCha20),
+    ChaCha20(ChaCha20),
+    ChaCha20Legacy(ChaCha20Legacy),
+}
+
+impl Cipher {
+    fn new(key: &[u8], nonce: &[u8]) -> Result<Self, DecryptError> {
+        if key.len() != 32 {
+            return Err(DecryptError::KeyLength(key.len()));
+        }
+        let key = key.into();
+        Ok(match nonce.len() {
+            24 => Cipher::XChaCha20(XChaCha20::new(key, nonce.into())),
+            12 => Cipher::ChaCha20(ChaCha20::new(key, nonce.into())),
+            8 => Cipher::ChaCha20Legacy(ChaCha20Legacy::new(key, nonce.into())),
+            len => return Err(DecryptError::UnsupportedNonce(len)),
+        })
+    }
+
+    fn apply_keystream(&mut self, buf: &mut [u8]) {
+        match self {
+            Cipher::XChaCha20(cipher) => cipher.apply_keystream(buf),
+            Cipher::ChaCha20(cipher) => cipher.apply_keystream(buf),
+            Cipher::ChaCha20Legacy(cipher) => cipher.apply_keystream(buf),
+        }
+    }
+}
+
+// Decrypts a PX4 `.ulge` log while reading, so it can be handed to `UlogReader`.
+pub struct UlogDecryptor<R> {
+    reader: R,
+    key_header: KeyHeader,
+    cipher: Cipher,
+}
+
+impl<R: Read> UlogDecryptor<R> {
+    pub fn new(mut reader: R, private_key: &RsaPrivateKey) -> Result<Self, DecryptError> {
+        let key_header = KeyHeader::read(&mut reader)?;
+        if key_header.exchange_algorithm != CRYPTO_RSA_OAEP {
+            return Err(DecryptError::UnsupportedAlgorithm(
+                key_header.exchange_algorithm,
+            ));
+        }
+        let key = private_key.decrypt(Oaep::new::<Sha256>(), &key_header.key)?;
+        let cipher = Cipher::new(&key, &key_header.nonce)?;
+        Ok(UlogDecryptor {
+            reader,
+            key_header,
+            cipher,
+        })
+    }
+
+    pub fn key_header(&self) -> &KeyHeader {
+        &self.key_header
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for UlogDecryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.cipher.apply_keystream(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R> fmt::Debug for UlogDecryptor<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UlogDecryptor")
+            .field("key_header", &self.key_header)
+            .finish_non_exhaustive()
+    }
+}
+
+pub fn decrypt(input: &[u8], private_key: &RsaPrivateKey) -> Result<Vec<u8>, DecryptError> {
+    let mut decryptor = UlogDecryptor::new(input, private_key)?;
+    let mut output = Vec::with_capacity(input.len() - decryptor.key_header().size());
+    decryptor.read_to_end(&mut output)?;
+    Ok(output)
+}
@@ -0,0 +1,159 @@
+use std::{error::Error, fmt, io, string::FromUtf8Error};
+
+use nom::{error::ErrorKind, IResult, Offset};
+
+use crate::MsgType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Messages(usize),
+    MessageSize(u16),
+    Bytes(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Messages(max) => write!(f, "more than {} messages", max),
+            Limit::MessageSize(max) => write!(f, "message larger than {} bytes", max),
+            Limit::Bytes(max) => write!(f, "more than {} bytes", max),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UlogErrorKind {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    MalformedMessageHeader { expected: MsgType, found: MsgType },
+    UnknownMessageType(u8),
+    MessageTooShort { msg_type: MsgType, msg_size: u16 },
+    InvalidAppendedOffset(u64),
+    UnknownIncompatFlags([u8; 8]),
+    LimitExceeded(Limit),
+    OrphanData(u16),
+    InvalidUtf8(FromUtf8Error),
+    Io(io::Error),
+    Parse(ErrorKind),
+}
+
+impl fmt::Display for UlogErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UlogErrorKind::BadMagic => write!(f, "input does not start with the ULog magic number"),
+            UlogErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported ULog version {}", version)
+            }
+            UlogErrorKind::Truncated => write!(f, "input ended in the middle of the log"),
+            UlogErrorKind::MalformedMessageHeader { expected, found } => {
+                write!(f, "expected message type {}, found {}", expected, found)
+            }
+            UlogErrorKind::UnknownMessageType(msg_type) => {
+                write!(f, "unknown message type {:?}", *msg_type as char)
+            }
+            UlogErrorKind::MessageTooShort { msg_type, msg_size } => write!(
+                f,
+                "message {} of size {} is too short for its fields",
+                msg_type, msg_size
+            ),
+            UlogErrorKind::InvalidAppendedOffset(offset) => {
+                write!(f, "invalid appended data offset {}", offset)
+            }
+            UlogErrorKind::UnknownIncompatFlags(flags) => {
+                write!(f, "unknown incompatible flag bits {:02x?}", flags)
+            }
+            UlogErrorKind::LimitExceeded(limit) => write!(f, "limit exceeded: {}", limit),
+            UlogErrorKind::OrphanData(msg_id) => {
+                write!(f, "data message for unsubscribed msg_id {}", msg_id)
+            }
+            UlogErrorKind::InvalidUtf8(err) => write!(f, "invalid UTF-8 string: {}", err),
+            UlogErrorKind::Io(err) => write!(f, "I/O error: {}", err),
+            UlogErrorKind::Parse(kind) => write!(f, "parse error: {}", kind.description()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UlogError {
+    pub kind: UlogErrorKind,
+    pub offset: usize,
+    pub message_index: Option<usize>,
+}
+
+impl UlogError {
+    pub fn new(err: nom::Err<ParseError<'_>>, start: &[u8]) -> Self {
+        match err {
+            nom::Err::Incomplete(_) => UlogError {
+                kind: UlogErrorKind::Truncated,
+                offset: start.len(),
+                message_index: None,
+            },
+            nom::Err::Error(err) | nom::Err::Failure(err) => UlogError {
+                kind: err.kind,
+                offset: start.offset(err.input),
+                message_index: err.message_index,
+            },
+        }
+    }
+}
+
+impl fmt::Display for UlogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if let Some(message_index) = self.message_index {
+            write!(f, " (message {})", message_index)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for UlogError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            UlogErrorKind::InvalidUtf8(err) => Some(err),
+            UlogErrorKind::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError<'a> {
+    pub input: &'a [u8],
+    pub kind: UlogErrorKind,
+    pub message_index: Option<usize>,
+}
+
+impl<'a> ParseError<'a> {
+    pub fn new(input: &'a [u8], kind: UlogErrorKind) -> Self {
+        ParseError {
+            input,
+            kind,
+            message_index: None,
+        }
+    }
+
+    pub fn at_message(self, message_index: usize) -> Self {
+        ParseError {
+            message_index: Some(message_index),
+            ..self
+        }
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a [u8]> for ParseError<'a> {
+    fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+        let kind = match kind {
+            ErrorKind::Eof | ErrorKind::Complete => UlogErrorKind::Truncated,
+            kind => UlogErrorKind::Parse(kind),
+        };
+        ParseError::new(input, kind)
+    }
+
+    fn append(_input: &'a [u8], _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+pub type ParseResult<'a, T> = IResult<&'a [u8], T, ParseError<'a>>;
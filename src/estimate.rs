@@ -0,0 +1,112 @@
+use std::{collections::HashMap, mem, ops::AddAssign};
+
+use crate::{DecodeError, FieldType, Layout, Message, Ulog};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    pub messages: usize,
+    pub message_bytes: usize,
+    pub dataset_bytes: usize,
+}
+
+impl AddAssign for MemoryEstimate {
+    fn add_assign(&mut self, other: Self) {
+        self.messages += other.messages;
+        self.message_bytes += other.message_bytes;
+        self.dataset_bytes += other.dataset_bytes;
+    }
+}
+
+fn row_bytes(layout: &Layout) -> usize {
+    let columns: usize = layout
+        .fields
+        .iter()
+        .map(|field| match (&field.field_type, field.array_len) {
+            (FieldType::Char, Some(len)) => mem::size_of::<String>() + len,
+            (FieldType::Char, None) => mem::size_of::<char>(),
+            (field_type, array_len) => field_type.size().unwrap_or(0) * array_len.unwrap_or(1),
+        })
+        .sum();
+    let timestamp = layout
+        .timestamp_field()
+        .map_or(0, |_| mem::size_of::<u64>());
+    columns + timestamp
+}
+
+impl MemoryEstimate {
+    fn new(layout: &Layout, messages: usize) -> Self {
+        MemoryEstimate {
+            messages,
+            message_bytes: messages * (mem::size_of::<Message>() + layout.size),
+            dataset_bytes: messages * row_bytes(layout),
+        }
+    }
+}
+
+impl Ulog {
+    // A metadata_only parse only keeps a summary of the data, a full parse keeps the messages,
+    // so both are counted per subscription.
+    fn estimates(&self) -> Result<Vec<(&str, u8, MemoryEstimate)>, DecodeError> {
+        let formats = self.formats()?;
+        let mut subscriptions = HashMap::new();
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        for (index, message) in self.data.iter().enumerate() {
+            let msg_id = match message {
+                Message::AddLogged(add_logged) => {
+                    subscriptions.insert(add_logged.msg_id, index);
+                    continue;
+                }
+                Message::Data(data) => data.msg_id,
+                Message::DataRef(data) => data.msg_id,
+                _ => continue,
+            };
+            if let Some(&added) = subscriptions.get(&msg_id) {
+                *counts.entry(added).or_default() += 1;
+            }
+        }
+        let mut estimates: Vec<(&str, u8, MemoryEstimate)> = Vec::new();
+        for (index, message) in self.data.iter().enumerate() {
+            let Message::AddLogged(add_logged) = message else {
+                continue;
+            };
+            let summarized = self
+                .data_summary
+                .get(&(add_logged.msg_id, index))
+                .map_or(0, |summary| summary.count);
+            let count = summarized + counts.get(&index).copied().unwrap_or(0);
+            if count == 0 {
+                continue;
+            }
+            let layout = formats.layout(&add_logged.message_name)?;
+            let estimate = MemoryEstimate::new(&layout, count);
+            match estimates.iter_mut().find(|(name, multi_id, _)| {
+                *name == &*add_logged.message_name && *multi_id == add_logged.multi_id
+            }) {
+                Some((_, _, total)) => *total += estimate,
+                None => estimates.push((&add_logged.message_name, add_logged.multi_id, estimate)),
+            }
+        }
+        Ok(estimates)
+    }
+
+    pub fn memory_estimate(&self) -> Result<MemoryEstimate, DecodeError> {
+        let mut total = MemoryEstimate::default();
+        for (_, _, estimate) in self.estimates()? {
+            total += estimate;
+        }
+        Ok(total)
+    }
+
+    pub fn topic_memory_estimate(
+        &self,
+        message_name: &str,
+        multi_id: u8,
+    ) -> Result<MemoryEstimate, DecodeError> {
+        Ok(self
+            .estimates()?
+            .into_iter()
+            .find(|&(name, id, _)| name == message_name && id == multi_id)
+            .map(|(_, _, estimate)| estimate)
+            .unwrap_or_default())
+    }
+}
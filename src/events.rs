@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::{value, DecodedData, FieldType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub timestamp: u64,
+    pub id: u32,
+    pub sequence: u16,
+    pub arguments: Vec<u8>,
+    pub log_levels: u8,
+}
+
+impl Event {
+    pub fn from_sample(sample: &DecodedData) -> Option<Self> {
+        Some(Event {
+            timestamp: sample.timestamp()?,
+            id: sample.get("id")?.as_u64()?.try_into().ok()?,
+            sequence: sample.get("event_sequence")?.as_u64()?.try_into().ok()?,
+            arguments: sample.get("arguments")?.as_u8_slice()?.to_vec(),
+            log_levels: sample.get("log_levels")?.as_u64()?.try_into().ok()?,
+        })
+    }
+
+    pub fn component_id(&self) -> u8 {
+        (self.id >> 24) as u8
+    }
+
+    pub fn sub_id(&self) -> u32 {
+        self.id & 0x00ff_ffff
+    }
+
+    pub fn log_level(&self) -> u8 {
+        self.log_levels & 0x0f
+    }
+
+    pub fn internal_log_level(&self) -> u8 {
+        self.log_levels >> 4
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventEnum {
+    pub base_type: FieldType,
+    pub entries: HashMap<u64, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventArgument {
+    pub name: String,
+    pub type_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventDefinition {
+    pub name: String,
+    pub message: String,
+    pub arguments: Vec<EventArgument>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventMetadata {
+    pub definitions: HashMap<u32, EventDefinition>,
+    pub enums: HashMap<String, EventEnum>,
+}
+
+impl EventMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        use serde_json::{Map, Value};
+
+        fn objects(value: &Value, key: &str) -> impl Iterator<Item = (String, Value)> {
+            value
+                .get(key)
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_else(Map::new)
+                .into_iter()
+        }
+
+        let root: Value = serde_json::from_str(json)?;
+        let mut metadata = EventMetadata::new();
+        for (component_id, component) in objects(&root, "components") {
+            let Ok(component_id) = component_id.parse::<u32>() else {
+                continue;
+            };
+            let namespace = component
+                .get("namespace")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            for (name, definition) in objects(&component, "enums") {
+                let base_type = definition
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .map(FieldType::from_name)
+                    .unwrap_or(FieldType::UInt8);
+                let entries = objects(&definition, "entries")
+                    .filter_map(|(value, entry)| {
+                        let name = entry.get("name").and_then(Value::as_str)?;
+                        Some((value.parse().ok()?, name.to_string()))
+                    })
+                    .collect();
+                let enumeration = EventEnum { base_type, entries };
+                metadata
+                    .enums
+                    .insert(format!("{}::{}", namespace, name), enumeration.clone());
+                metadata.enums.insert(name, enumeration);
+            }
+            for (_, group) in objects(&component, "event_groups") {
+                for (sub_id, event) in objects(&group, "events") {
+                    let Ok(sub_id) = sub_id.parse::<u32>() else {
+                        continue;
+                    };
+                    let arguments = event
+                        .get("arguments")
+                        .and_then(Value::as_array)
+                        .map(|arguments| {
+                            arguments
+                                .iter()
+                                .map(|argument| EventArgument {
+                                    name: argument
+                                        .get("name")
+                                        .and_then(Value::as_str)
+                                        .unwrap_or_default()
+                                        .to_string(),
+                                    type_name: argument
+                                        .get("type")
+                                        .and_then(Value::as_str)
+                                        .unwrap_or_default()
+                                        .to_string(),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    let text = |key| {
+                        event
+                            .get(key)
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string()
+                    };
+                    metadata.definitions.insert(
+                        (component_id << 24) | sub_id,
+                        EventDefinition {
+                            name: text("name"),
+                            message: text("message"),
+                            arguments,
+                        },
+                    );
+                }
+            }
+        }
+        Ok(metadata)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&EventDefinition> {
+        self.definitions.get(&id)
+    }
+
+    pub fn arguments(&self, event: &Event) -> Option<Vec<String>> {
+        let definition = self.get(event.id)?;
+        let mut input = event.arguments.as_slice();
+        let mut arguments = Vec::new();
+        for argument in &definition.arguments {
+            let enumeration = self.enums.get(&argument.type_name);
+            let field_type = match enumeration {
+                Some(enumeration) => enumeration.base_type.clone(),
+                None => FieldType::from_name(&argument.type_name),
+            };
+            let (rest, value) = value(input, &field_type, None).ok()?;
+            input = rest;
+            let entry = enumeration
+                .zip(value.as_u64())
+                .and_then(|(enumeration, key)| enumeration.entries.get(&key));
+            arguments.push(match entry {
+                Some(name) => name.clone(),
+                None => value.to_string(),
+            });
+        }
+        Some(arguments)
+    }
+
+    pub fn render(&self, event: &Event) -> Option<String> {
+        let definition = self.get(event.id)?;
+        let arguments = self.arguments(event)?;
+        let mut message = String::new();
+        let mut rest = definition.message.as_str();
+        while let Some(start) = rest.find('{') {
+            message.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}') else {
+                rest = &rest[start..];
+                break;
+            };
+            let placeholder = &rest[start + 1..start + end];
+            let index = placeholder.split(':').next().unwrap_or_default();
+            match index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| arguments.get(index.checked_sub(1)?))
+            {
+                Some(argument) => message.push_str(argument),
+                None => message.push_str(&rest[start..=start + end]),
+            }
+            rest = &rest[start + end + 1..];
+        }
+        message.push_str(rest);
+        Some(message)
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{Column, ColumnData, Dataset, DecodeError, FormatError, Ulog};
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Decode(DecodeError),
+    #[cfg(feature = "arrow")]
+    Arrow(arrow::error::ArrowError),
+    #[cfg(feature = "hdf5")]
+    Hdf5(hdf5_pure::Error),
+    #[cfg(feature = "mcap")]
+    Mcap(mcap::McapError),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    #[cfg(feature = "polars")]
+    Polars(polars::error::PolarsError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(err) => write!(f, "I/O error: {}", err),
+            ExportError::Decode(err) => write!(f, "{}", err),
+            #[cfg(feature = "arrow")]
+            ExportError::Arrow(err) => write!(f, "Arrow error: {}", err),
+            #[cfg(feature = "hdf5")]
+            ExportError::Hdf5(err) => write!(f, "HDF5 error: {}", err),
+            #[cfg(feature = "mcap")]
+            ExportError::Mcap(err) => write!(f, "MCAP error: {}", err),
+            #[cfg(feature = "parquet")]
+            ExportError::Parquet(err) => write!(f, "Parquet error: {}", err),
+            #[cfg(feature = "polars")]
+            ExportError::Polars(err) => write!(f, "polars error: {}", err),
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::Io(err) => Some(err),
+            ExportError::Decode(err) => Some(err),
+            #[cfg(feature = "arrow")]
+            ExportError::Arrow(err) => Some(err),
+            #[cfg(feature = "hdf5")]
+            ExportError::Hdf5(err) => Some(err),
+            #[cfg(feature = "mcap")]
+            ExportError::Mcap(err) => Some(err),
+            #[cfg(feature = "parquet")]
+            ExportError::Parquet(err) => Some(err),
+            #[cfg(feature = "polars")]
+            ExportError::Polars(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> Self {
+        ExportError::Io(err)
+    }
+}
+
+impl From<DecodeError> for ExportError {
+    fn from(err: DecodeError) -> Self {
+        ExportError::Decode(err)
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl From<arrow::error::ArrowError> for ExportError {
+    fn from(err: arrow::error::ArrowError) -> Self {
+        ExportError::Arrow(err)
+    }
+}
+
+#[cfg(feature = "hdf5")]
+impl From<hdf5_pure::Error> for ExportError {
+    fn from(err: hdf5_pure::Error) -> Self {
+        ExportError::Hdf5(err)
+    }
+}
+
+#[cfg(feature = "mcap")]
+impl From<mcap::McapError> for ExportError {
+    fn from(err: mcap::McapError) -> Self {
+        ExportError::Mcap(err)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for ExportError {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        ExportError::Parquet(err)
+    }
+}
+
+#[cfg(feature = "polars")]
+impl From<polars::error::PolarsError> for ExportError {
+    fn from(err: polars::error::PolarsError) -> Self {
+        ExportError::Polars(err)
+    }
+}
+
+impl From<FormatError> for ExportError {
+    fn from(err: FormatError) -> Self {
+        ExportError::Decode(DecodeError::Format(err))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub topics: Option<BTreeSet<String>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            topics: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topics
+            .get_or_insert_with(BTreeSet::new)
+            .insert(topic.into());
+        self
+    }
+}
+
+// One CSV cell: an element of a column, or the whole value for scalar columns.
+struct Cell<'a> {
+    column: &'a Column,
+    element: usize,
+    stride: usize,
+}
+
+impl Cell<'_> {
+    fn header(&self) -> String {
+        match self.column.array_len {
+            Some(_) => format!("{}[{}]", self.column.name, self.element),
+            None => self.column.name.clone(),
+        }
+    }
+
+    fn write(&self, writer: &mut impl Write, row: usize) -> io::Result<()> {
+        let index = row * self.stride + self.element;
+        match &self.column.data {
+            ColumnData::Int8(v) => write!(writer, "{}", v[index]),
+            ColumnData::UInt8(v) => write!(writer, "{}", v[index]),
+            ColumnData::Int16(v) => write!(writer, "{}", v[index]),
+            ColumnData::UInt16(v) => write!(writer, "{}", v[index]),
+            ColumnData::Int32(v) => write!(writer, "{}", v[index]),
+            ColumnData::UInt32(v) => write!(writer, "{}", v[index]),
+            ColumnData::Int64(v) => write!(writer, "{}", v[index]),
+            ColumnData::UInt64(v) => write!(writer, "{}", v[index]),
+            ColumnData::Float(v) => write!(writer, "{}", v[index]),
+            ColumnData::Double(v) => write!(writer, "{}", v[index]),
+            ColumnData::Bool(v) => write!(writer, "{}", u8::from(v[index])),
+            ColumnData::Char(v) => write!(writer, "{}", v[index] as u32),
+            // Strings are stored per row, so `element` is the byte within the string
+            // and the stride is one.
+            ColumnData::String(v) => {
+                let byte = v[row].as_bytes().get(self.element).copied();
+                write!(writer, "{}", byte.unwrap_or(0))
+            }
+        }
+    }
+}
+
+fn cells(dataset: &Dataset) -> Vec<Cell<'_>> {
+    let mut columns: Vec<&Column> = dataset
+        .columns
+        .iter()
+        .filter(|column| !column.name.starts_with("_padding"))
+        .collect();
+    if let Some(index) = columns.iter().position(|column| column.name == "timestamp") {
+        let timestamp = columns.remove(index);
+        columns.insert(0, timestamp);
+    }
+    columns
+        .into_iter()
+        .flat_map(|column| {
+            let stride = column.stride();
+            (0..column.array_len.unwrap_or(1)).map(move |element| Cell {
+                column,
+                element,
+                stride,
+            })
+        })
+        .collect()
+}
+
+// Matches pyulog's ulog2csv: the timestamp comes first, arrays are expanded into
+// `name[i]` columns, padding is left out and bools and chars are written as numbers.
+pub fn write_csv(dataset: &Dataset, mut writer: impl Write, delimiter: char) -> io::Result<()> {
+    let cells = cells(dataset);
+    let header: Vec<String> = cells.iter().map(Cell::header).collect();
+    writeln!(writer, "{}", header.join(&delimiter.to_string()))?;
+    for row in 0..dataset.len() {
+        for (i, cell) in cells.iter().enumerate() {
+            if i > 0 {
+                write!(writer, "{}", delimiter)?;
+            }
+            cell.write(&mut writer, row)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+impl Ulog {
+    // Every subscribed topic instance, ordered by name and multi_id.
+    pub(crate) fn topic_instances(&self) -> BTreeSet<(String, u8)> {
+        self.subscriptions()
+            .subscriptions
+            .iter()
+            .map(|subscription| (subscription.message_name.to_string(), subscription.multi_id))
+            .collect()
+    }
+
+    // Writes `<base_name>_<topic>_<multi_id>.csv` for every topic instance into
+    // `output_dir` and returns the paths in the order they were written.
+    pub fn export_csv(
+        &self,
+        output_dir: impl AsRef<Path>,
+        base_name: &str,
+        options: &CsvOptions,
+    ) -> Result<Vec<PathBuf>, ExportError> {
+        let instances: Vec<_> = self
+            .topic_instances()
+            .into_iter()
+            .filter(|(message_name, _)| {
+                options
+                    .topics
+                    .as_ref()
+                    .is_none_or(|topics| topics.contains(message_name))
+            })
+            .collect();
+
+        let mut paths = Vec::with_capacity(instances.len());
+        for (message_name, multi_id) in instances {
+            let dataset = self.dataset(&message_name, multi_id)?;
+            let path = output_dir
+                .as_ref()
+                .join(format!("{}_{}_{}.csv", base_name, message_name, multi_id));
+            let mut writer = BufWriter::new(File::create(&path)?);
+            write_csv(&dataset, &mut writer, options.delimiter)?;
+            writer.flush()?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, error::Error, fmt, str};
+
+use nom::{
+    bytes::complete::{tag, take_while1},
+    character::complete::{char, digit1, space1},
+    combinator::{eof, opt},
+    error::ErrorKind,
+    multi::many_till,
+    sequence::{delimited, terminated},
+};
+
+use crate::{MessageFormat, ParseError, ParseResult, UlogError, UlogErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float,
+    Double,
+    Bool,
+    Char,
+    Nested(String),
+}
+
+impl FieldType {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "int8_t" => FieldType::Int8,
+            "uint8_t" => FieldType::UInt8,
+            "int16_t" => FieldType::Int16,
+            "uint16_t" => FieldType::UInt16,
+            "int32_t" => FieldType::Int32,
+            "uint32_t" => FieldType::UInt32,
+            "int64_t" => FieldType::Int64,
+            "uint64_t" => FieldType::UInt64,
+            "float" => FieldType::Float,
+            "double" => FieldType::Double,
+            "bool" => FieldType::Bool,
+            "char" => FieldType::Char,
+            name => FieldType::Nested(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            FieldType::Int8 => "int8_t",
+            FieldType::UInt8 => "uint8_t",
+            FieldType::Int16 => "int16_t",
+            FieldType::UInt16 => "uint16_t",
+            FieldType::Int32 => "int32_t",
+            FieldType::UInt32 => "uint32_t",
+            FieldType::Int64 => "int64_t",
+            FieldType::UInt64 => "uint64_t",
+            FieldType::Float => "float",
+            FieldType::Double => "double",
+            FieldType::Bool => "bool",
+            FieldType::Char => "char",
+            FieldType::Nested(name) => name,
+        }
+    }
+
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            FieldType::Int8 | FieldType::UInt8 | FieldType::Bool | FieldType::Char => Some(1),
+            FieldType::Int16 | FieldType::UInt16 => Some(2),
+            FieldType::Int32 | FieldType::UInt32 | FieldType::Float => Some(4),
+            FieldType::Int64 | FieldType::UInt64 | FieldType::Double => Some(8),
+            FieldType::Nested(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub field_type: FieldType,
+    pub array_len: Option<usize>,
+}
+
+impl Field {
+    pub fn parse_key(key: &str) -> Option<Self> {
+        match declaration(key.as_bytes()) {
+            Ok(([], field)) => Some(field),
+            _ => None,
+        }
+    }
+
+    pub fn is_padding(&self) -> bool {
+        self.name.starts_with("_padding")
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.field_type.name())?;
+        if let Some(len) = self.array_len {
+            write!(f, "[{}]", len)?;
+        }
+        write!(f, " {}", self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatDefinition {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+impl FormatDefinition {
+    pub fn parse(format: &str) -> Result<Self, UlogError> {
+        format_definition(format.as_bytes())
+            .map(|(_, definition)| definition)
+            .map_err(|err| UlogError::new(err, format.as_bytes()))
+    }
+}
+
+impl fmt::Display for FormatDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        for field in &self.fields {
+            write!(f, "{};", field)?;
+        }
+        Ok(())
+    }
+}
+
+impl MessageFormat {
+    pub fn definition(&self) -> Result<FormatDefinition, UlogError> {
+        FormatDefinition::parse(&self.format)
+    }
+}
+
+fn identifier(input: &[u8]) -> ParseResult<'_, String> {
+    let (input, name) = take_while1(|c: u8| c.is_ascii_alphanumeric() || c == b'_')(input)?;
+    Ok((input, String::from_utf8_lossy(name).into_owned()))
+}
+
+fn array_len(input: &[u8]) -> ParseResult<'_, usize> {
+    let (rest, digits) = delimited(char('['), digit1, char(']'))(input)?;
+    let len = str::from_utf8(digits)
+        .ok()
+        .and_then(|digits| digits.parse().ok())
+        .ok_or_else(|| {
+            nom::Err::Failure(ParseError::new(
+                input,
+                UlogErrorKind::Parse(ErrorKind::Digit),
+            ))
+        })?;
+    Ok((rest, len))
+}
+
+pub fn declaration(input: &[u8]) -> ParseResult<'_, Field> {
+    let (input, type_name) = identifier(input)?;
+    let (input, type_len) = opt(array_len)(input)?;
+    let (input, _) = space1(input)?;
+    let (input, name) = identifier(input)?;
+    let (input, name_len) = opt(array_len)(input)?;
+    Ok((
+        input,
+        Field {
+            name,
+            field_type: FieldType::from_name(&type_name),
+            array_len: type_len.or(name_len),
+        },
+    ))
+}
+
+pub fn field(input: &[u8]) -> ParseResult<'_, Field> {
+    terminated(declaration, char(';'))(input)
+}
+
+pub fn format_definition(input: &[u8]) -> ParseResult<'_, FormatDefinition> {
+    let (input, name) = identifier(input)?;
+    let (input, _) = tag(":")(input)?;
+    let (input, (fields, _)) = many_till(field, eof)(input)?;
+    Ok((input, FormatDefinition { name, fields }))
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Invalid { format: String, error: UlogError },
+    UnknownType { format: String, type_name: String },
+    Recursive(String),
+    TooLarge(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Invalid { format, error } => {
+                write!(f, "invalid format {:?}: {}", format, error)
+            }
+            FormatError::UnknownType { format, type_name } => {
+                write!(f, "format {:?} uses unknown type {:?}", format, type_name)
+            }
+            FormatError::Recursive(format) => write!(f, "format {:?} contains itself", format),
+            FormatError::TooLarge(format) => write!(
+                f,
+                "format {:?} is larger than a message can hold ({} bytes)",
+                format, MAX_LAYOUT_SIZE
+            ),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FormatError::Invalid { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Data messages carry their size in a u16, so no valid layout can be larger.
+const MAX_LAYOUT_SIZE: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutField {
+    pub name: String,
+    pub field_type: FieldType,
+    pub array_len: Option<usize>,
+    pub offset: usize,
+}
+
+impl LayoutField {
+    pub fn size(&self) -> usize {
+        // Saturates so hand-built fields cannot overflow; layouts from `Formats` are
+        // bounded by MAX_LAYOUT_SIZE.
+        self.field_type
+            .size()
+            .unwrap_or(0)
+            .saturating_mul(self.array_len.unwrap_or(1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub name: String,
+    pub fields: Vec<LayoutField>,
+    pub size: usize,
+}
+
+impl Layout {
+    pub fn min_size(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|field| field.offset.saturating_add(field.size()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&LayoutField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn project<'a>(&self, names: &[&'a str]) -> Result<Layout, &'a str> {
+        let mut fields: Vec<LayoutField> = self.timestamp_field().into_iter().cloned().collect();
+        for &name in names {
+            let field = self.field(name).ok_or(name)?;
+            if !fields.contains(field) {
+                fields.push(field.clone());
+            }
+        }
+        Ok(Layout {
+            name: self.name.clone(),
+            fields,
+            size: self.size,
+        })
+    }
+
+    pub fn timestamp_field(&self) -> Option<&LayoutField> {
+        self.field("timestamp")
+            .filter(|field| field.field_type == FieldType::UInt64 && field.array_len.is_none())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Formats {
+    pub definitions: HashMap<String, FormatDefinition>,
+}
+
+impl Formats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, definition: FormatDefinition) {
+        self.definitions.insert(definition.name.clone(), definition);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FormatDefinition> {
+        self.definitions.get(name)
+    }
+
+    pub fn layout(&self, name: &str) -> Result<Layout, FormatError> {
+        let mut fields = Vec::new();
+        let size = self.flatten(name, "", 0, &mut Vec::new(), &mut fields)?;
+        Ok(Layout {
+            name: name.to_string(),
+            fields,
+            size,
+        })
+    }
+
+    // Sizes and offsets come from the file, so every step is checked against
+    // MAX_LAYOUT_SIZE before anything is expanded.
+    fn flatten<'a>(
+        &'a self,
+        name: &'a str,
+        prefix: &str,
+        mut offset: usize,
+        stack: &mut Vec<&'a str>,
+        fields: &mut Vec<LayoutField>,
+    ) -> Result<usize, FormatError> {
+        if stack.contains(&name) {
+            return Err(FormatError::Recursive(name.to_string()));
+        }
+        let definition = self.get(name).ok_or_else(|| FormatError::UnknownType {
+            format: stack.last().unwrap_or(&name).to_string(),
+            type_name: name.to_string(),
+        })?;
+        let root: &'a str = stack.first().copied().unwrap_or(name);
+        let too_large = || FormatError::TooLarge(root.to_string());
+        let advance = |offset: usize, size: usize, count: usize| {
+            size.checked_mul(count)
+                .and_then(|size| offset.checked_add(size))
+                .filter(|&end| end <= MAX_LAYOUT_SIZE)
+                .ok_or_else(too_large)
+        };
+        stack.push(name);
+        for field in &definition.fields {
+            match &field.field_type {
+                field_type if field.is_padding() => {
+                    let size = field_type.size().unwrap_or(0);
+                    offset = advance(offset, size, field.array_len.unwrap_or(1))?;
+                }
+                FieldType::Nested(nested) => match field.array_len {
+                    Some(0) => {}
+                    Some(len) => {
+                        // The first element gives the element size, which bounds the
+                        // whole array before the remaining elements are expanded.
+                        let start = offset;
+                        let count = fields.len();
+                        let prefix_0 = format!("{}{}[0].", prefix, field.name);
+                        offset = self.flatten(nested, &prefix_0, offset, stack, fields)?;
+                        advance(start, offset - start, len)?;
+                        if fields.len() == count && offset == start {
+                            continue;
+                        }
+                        for i in 1..len {
+                            let prefix = format!("{}{}[{}].", prefix, field.name, i);
+                            offset = self.flatten(nested, &prefix, offset, stack, fields)?;
+                        }
+                    }
+                    None => {
+                        let prefix = format!("{}{}.", prefix, field.name);
+                        offset = self.flatten(nested, &prefix, offset, stack, fields)?;
+                    }
+                },
+                field_type => {
+                    let field = LayoutField {
+                        name: format!("{}{}", prefix, field.name),
+                        field_type: field_type.clone(),
+                        array_len: field.array_len,
+                        offset,
+                    };
+                    let size = field.field_type.size().unwrap_or(0);
+                    offset = advance(offset, size, field.array_len.unwrap_or(1))?;
+                    // Zero-length arrays take no space, so bound the field count too.
+                    if fields.len() >= MAX_LAYOUT_SIZE {
+                        return Err(too_large());
+                    }
+                    fields.push(field);
+                }
+            }
+        }
+        stack.pop();
+        Ok(offset)
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use hdf5_pure::{AttrValue, DatasetBuilder, FileBuilder};
+
+use crate::{Column, ColumnData, ExportError, ParameterValue, Ulog, UlogValue};
+
+fn attr_value(value: &UlogValue) -> AttrValue {
+    match value {
+        UlogValue::Int8(v) => AttrValue::I8(*v),
+        UlogValue::UInt8(v) => AttrValue::U8(*v),
+        UlogValue::Int16(v) => AttrValue::I16(*v),
+        UlogValue::UInt16(v) => AttrValue::U16(*v),
+        UlogValue::Int32(v) => AttrValue::I32(*v),
+        UlogValue::UInt32(v) => AttrValue::U32(*v),
+        UlogValue::Int64(v) => AttrValue::I64(*v),
+        UlogValue::UInt64(v) => AttrValue::U64(*v),
+        UlogValue::Float(v) => AttrValue::F32(*v),
+        UlogValue::Double(v) => AttrValue::F64(*v),
+        UlogValue::Bool(v) => AttrValue::U8(u8::from(*v)),
+        UlogValue::Char(v) => AttrValue::String(v.to_string()),
+        UlogValue::Int8Array(v) => AttrValue::I8Array(v.clone()),
+        UlogValue::UInt8Array(v) => AttrValue::U8Array(v.clone()),
+        UlogValue::Int16Array(v) => AttrValue::I16Array(v.clone()),
+        UlogValue::UInt16Array(v) => AttrValue::U16Array(v.clone()),
+        UlogValue::Int32Array(v) => AttrValue::I32Array(v.clone()),
+        UlogValue::UInt32Array(v) => AttrValue::U32Array(v.clone()),
+        UlogValue::Int64Array(v) => AttrValue::I64Array(v.clone()),
+        UlogValue::UInt64Array(v) => AttrValue::U64Array(v.clone()),
+        UlogValue::FloatArray(v) => AttrValue::F32Array(v.clone()),
+        UlogValue::DoubleArray(v) => AttrValue::F64Array(v.clone()),
+        UlogValue::BoolArray(v) => AttrValue::U8Array(v.iter().map(|&v| u8::from(v)).collect()),
+        UlogValue::CharArray(v) => AttrValue::String(v.iter().collect()),
+        UlogValue::String(v) => AttrValue::String(v.clone()),
+    }
+}
+
+// Array fields get a second dimension, char arrays are stored as one string per
+// sample and bools and single chars as u8.
+fn write_column(dataset: &mut DatasetBuilder, column: &Column) {
+    match &column.data {
+        ColumnData::Int8(v) => dataset.with_i8_data(v),
+        ColumnData::UInt8(v) => dataset.with_u8_data(v),
+        ColumnData::Int16(v) => dataset.with_i16_data(v),
+        ColumnData::UInt16(v) => dataset.with_u16_data(v),
+        ColumnData::Int32(v) => dataset.with_i32_data(v),
+        ColumnData::UInt32(v) => dataset.with_u32_data(v),
+        ColumnData::Int64(v) => dataset.with_i64_data(v),
+        ColumnData::UInt64(v) => dataset.with_u64_data(v),
+        ColumnData::Float(v) => dataset.with_f32_data(v),
+        ColumnData::Double(v) => dataset.with_f64_data(v),
+        ColumnData::Bool(v) => {
+            dataset.with_u8_data(&v.iter().map(|&v| u8::from(v)).collect::<Vec<_>>())
+        }
+        ColumnData::Char(v) => {
+            dataset.with_u8_data(&v.iter().map(|&c| c as u8).collect::<Vec<_>>())
+        }
+        ColumnData::String(v) => {
+            let values: Vec<&str> = v.iter().map(String::as_str).collect();
+            dataset.with_vlen_strings(&values)
+        }
+    };
+    if column.array_len.is_some() && !matches!(column.data, ColumnData::String(_)) {
+        dataset.with_shape(&[column.len() as u64, column.stride() as u64]);
+    }
+}
+
+impl Ulog {
+    // The layout is:
+    //
+    // /                          attributes: ulog_version, ulog_timestamp and the info
+    // /parameters                attributes: the initial parameter values
+    // /<topic>_<multi_id>        attributes: message_name and multi_id
+    // /<topic>_<multi_id>/<field> one dataset per field, one row per sample
+    pub fn write_hdf5(&self, writer: impl Write) -> Result<(), ExportError> {
+        let mut file = FileBuilder::new();
+        file.set_attr("ulog_version", AttrValue::U8(self.header.version));
+        file.set_attr("ulog_timestamp", AttrValue::U64(self.header.timestamp));
+        for (name, value) in self.info() {
+            file.set_attr(&name, attr_value(&value));
+        }
+
+        let mut parameters = file.create_group("parameters");
+        for (name, value) in self.parameters() {
+            let value = match value {
+                ParameterValue::Float(v) => AttrValue::F32(v),
+                ParameterValue::Int32(v) => AttrValue::I32(v),
+            };
+            parameters.set_attr(&name, value);
+        }
+        file.add_group(parameters.finish());
+
+        for (message_name, multi_id) in self.topic_instances() {
+            let dataset = self.dataset(&message_name, multi_id)?;
+            let mut group = file.create_group(&format!("{}_{}", message_name, multi_id));
+            group.set_attr("message_name", AttrValue::String(message_name));
+            group.set_attr("multi_id", AttrValue::U8(multi_id));
+            for column in dataset
+                .columns
+                .iter()
+                .filter(|column| !column.name.starts_with("_padding"))
+            {
+                write_column(group.create_dataset(&column.name), column);
+            }
+            file.add_group(group.finish());
+        }
+        file.finish_to(writer)?;
+        Ok(())
+    }
+
+    pub fn export_hdf5(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        self.write_hdf5(BufWriter::new(File::create(path)?))
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, sync::Arc};
+
+use nom::Offset;
+
+use crate::{
+    borrowed_message_body, decode::decode_layout, message_frame, BorrowedMessage, BorrowedMessages,
+    DecodeError, DecodedData, FormatDefinition, Formats, ParseOptions, UlogError, MAGIC,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub offset: usize,
+    pub msg_id: u16,
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicIndex {
+    pub message_name: String,
+    pub multi_id: u8,
+    pub entries: Vec<IndexEntry>,
+}
+
+impl TopicIndex {
+    pub fn time_range(&self) -> Option<(u64, u64)> {
+        let first = self.entries.iter().find_map(|entry| entry.timestamp)?;
+        let last = self
+            .entries
+            .iter()
+            .rev()
+            .find_map(|entry| entry.timestamp)?;
+        Some((first, last))
+    }
+
+    pub fn range(&self, start: u64, end: u64) -> &[IndexEntry] {
+        let from = self
+            .entries
+            .partition_point(|entry| entry.timestamp < Some(start));
+        let to = self
+            .entries
+            .partition_point(|entry| entry.timestamp < Some(end));
+        &self.entries[from..to.max(from)]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UlogIndex {
+    pub formats: Formats,
+    pub topics: Vec<TopicIndex>,
+    pub sync_offsets: Vec<usize>,
+    pub indexed_bytes: usize,
+    pub log_len: usize,
+    pub log_timestamp: u64,
+    pub log_hash: u64,
+}
+
+pub(crate) const HASHED_LEN: usize = 4096;
+
+// FNV-1a over the first and last bytes of the log, cheap enough to check before
+// reusing an index.
+pub(crate) fn edge_hash(head: &[u8], tail: &[u8]) -> u64 {
+    head.iter()
+        .chain(tail)
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+pub(crate) fn log_hash(input: &[u8]) -> u64 {
+    let len = input.len().min(HASHED_LEN);
+    edge_hash(&input[..len], &input[input.len() - len..])
+}
+
+impl UlogIndex {
+    pub fn build(input: &[u8]) -> Result<Self, UlogError> {
+        let options = ParseOptions::default();
+        let messages = BorrowedMessages::new(input, &options)?;
+        let mut rest = messages.rest();
+        let mut index = UlogIndex {
+            log_len: input.len(),
+            log_timestamp: messages.header.timestamp,
+            log_hash: log_hash(input),
+            ..Self::default()
+        };
+        let mut topic_ids: HashMap<(String, u8), usize> = HashMap::new();
+        let mut timestamp_offsets: HashMap<String, Option<usize>> = HashMap::new();
+        let mut active: HashMap<u16, (usize, Option<usize>)> = HashMap::new();
+        while !rest.starts_with(&MAGIC) {
+            let Ok((next, (header, body))) = message_frame(rest) else {
+                break;
+            };
+            let offset = input.offset(rest);
+            match borrowed_message_body(body, header, &options) {
+                Ok((_, BorrowedMessage::Format { format, .. })) => {
+                    if let Ok(definition) = FormatDefinition::parse(&format) {
+                        index.formats.insert(definition);
+                        timestamp_offsets.clear();
+                    }
+                }
+                Ok((
+                    _,
+                    BorrowedMessage::AddLogged {
+                        multi_id,
+                        msg_id,
+                        message_name,
+                        ..
+                    },
+                )) => {
+                    let timestamp_offset = *timestamp_offsets
+                        .entry(message_name.to_string())
+                        .or_insert_with(|| {
+                            let layout = index.formats.layout(&message_name).ok()?;
+                            Some(layout.timestamp_field()?.offset)
+                        });
+                    let topics = &mut index.topics;
+                    let topic = *topic_ids
+                        .entry((message_name.to_string(), multi_id))
+                        .or_insert_with(|| {
+                            topics.push(TopicIndex {
+                                message_name: message_name.to_string(),
+                                multi_id,
+                                entries: Vec::new(),
+                            });
+                            topics.len() - 1
+                        });
+                    active.insert(msg_id, (topic, timestamp_offset));
+                }
+                Ok((_, BorrowedMessage::RemoveLogged { msg_id, .. })) => {
+                    active.remove(&msg_id);
+                }
+                Ok((_, BorrowedMessage::Data { msg_id, data, .. })) => {
+                    if let Some(&(topic, timestamp_offset)) = active.get(&msg_id) {
+                        let timestamp = timestamp_offset
+                            .and_then(|offset| data.get(offset..offset + 8))
+                            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+                        index.topics[topic].entries.push(IndexEntry {
+                            offset,
+                            msg_id,
+                            timestamp,
+                        });
+                    }
+                }
+                Ok((_, BorrowedMessage::Sync { .. })) => index.sync_offsets.push(offset),
+                _ => {}
+            }
+            rest = next;
+        }
+        index.indexed_bytes = input.offset(rest);
+        Ok(index)
+    }
+
+    pub fn topic(&self, message_name: &str, multi_id: u8) -> Option<&TopicIndex> {
+        self.topics
+            .iter()
+            .find(|topic| topic.message_name == message_name && topic.multi_id == multi_id)
+    }
+
+    pub fn samples(
+        &self,
+        input: &[u8],
+        message_name: &str,
+        multi_id: u8,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<DecodedData>, DecodeError> {
+        let Some(topic) = self.topic(message_name, multi_id) else {
+            return Ok(Vec::new());
+        };
+        let layout = Arc::new(self.formats.layout(message_name)?);
+        topic
+            .range(start, end)
+            .iter()
+            .map(|entry| {
+                let frame = input.get(entry.offset..).unwrap_or_default();
+                let (_, (_, body)) = message_frame(frame).map_err(|err| DecodeError::Data {
+                    msg_id: entry.msg_id,
+                    error: UlogError::new(err, input),
+                })?;
+                let payload = body.get(2..).unwrap_or_default();
+                decode_layout(entry.msg_id, multi_id, &layout, payload)
+            })
+            .collect()
+    }
+}
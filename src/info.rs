@@ -0,0 +1,35 @@
+use crate::{value, Field, MessageInfo, MessageInfoMultiple, UlogValue};
+
+pub fn typed_value(key: &str, bytes: &[u8]) -> Option<UlogValue> {
+    let field = Field::parse_key(key)?;
+    value(bytes, &field.field_type, field.array_len)
+        .ok()
+        .map(|(_, value)| value)
+}
+
+pub(crate) fn concatenated_value(key: &str, bytes: &[u8]) -> Option<UlogValue> {
+    let field = Field::parse_key(key)?;
+    let array_len = match field.array_len {
+        Some(_) => Some(bytes.len() / field.field_type.size()?),
+        None => None,
+    };
+    value(bytes, &field.field_type, array_len)
+        .ok()
+        .map(|(_, value)| value)
+}
+
+impl MessageInfo {
+    pub fn name(&self) -> Option<&str> {
+        self.key.split_once(' ').map(|(_, name)| name)
+    }
+
+    pub fn typed_value(&self) -> Option<UlogValue> {
+        typed_value(&self.key, &self.value)
+    }
+}
+
+impl MessageInfoMultiple {
+    pub fn name(&self) -> Option<&str> {
+        self.key.split_once(' ').map(|(_, name)| name)
+    }
+}
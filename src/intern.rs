@@ -0,0 +1,30 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::Message;
+
+#[derive(Debug, Default)]
+pub(crate) struct StringPool {
+    strings: HashSet<Arc<str>>,
+}
+
+impl StringPool {
+    fn intern(&mut self, string: &mut Arc<str>) {
+        match self.strings.get(&**string) {
+            Some(interned) => *string = interned.clone(),
+            None => {
+                self.strings.insert(string.clone());
+            }
+        }
+    }
+
+    pub(crate) fn intern_message(&mut self, message: &mut Message) {
+        match message {
+            Message::Info(info) => self.intern(&mut info.key),
+            Message::InfoMultiple(info) => self.intern(&mut info.key),
+            Message::Parameter(parameter) => self.intern(&mut parameter.key),
+            Message::ParameterDefault(parameter) => self.intern(&mut parameter.key),
+            Message::AddLogged(add_logged) => self.intern(&mut add_logged.message_name),
+            _ => {}
+        }
+    }
+}
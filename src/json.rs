@@ -0,0 +1,270 @@
+use std::io::{self, Write};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    Column, ColumnData, DecodeError, DecodedData, Decoder, ExportError, Message, ParameterValue,
+    Ulog, UlogValue,
+};
+
+// f32 goes through its shortest representation so 0.1 stays 0.1 rather than
+// 0.10000000149011612. Non-finite values become null.
+fn float(v: f32) -> Value {
+    Value::from(v.to_string().parse::<f64>().unwrap_or(f64::NAN))
+}
+
+fn array<T: Copy>(values: &[T], f: impl Fn(T) -> Value) -> Value {
+    Value::Array(values.iter().map(|&v| f(v)).collect())
+}
+
+fn ulog_value(value: &UlogValue) -> Value {
+    match value {
+        UlogValue::Int8(v) => Value::from(*v),
+        UlogValue::UInt8(v) => Value::from(*v),
+        UlogValue::Int16(v) => Value::from(*v),
+        UlogValue::UInt16(v) => Value::from(*v),
+        UlogValue::Int32(v) => Value::from(*v),
+        UlogValue::UInt32(v) => Value::from(*v),
+        UlogValue::Int64(v) => Value::from(*v),
+        UlogValue::UInt64(v) => Value::from(*v),
+        UlogValue::Float(v) => float(*v),
+        UlogValue::Double(v) => Value::from(*v),
+        UlogValue::Bool(v) => Value::from(*v),
+        UlogValue::Char(v) => Value::from(v.to_string()),
+        UlogValue::Int8Array(v) => array(v, Value::from),
+        UlogValue::UInt8Array(v) => array(v, Value::from),
+        UlogValue::Int16Array(v) => array(v, Value::from),
+        UlogValue::UInt16Array(v) => array(v, Value::from),
+        UlogValue::Int32Array(v) => array(v, Value::from),
+        UlogValue::UInt32Array(v) => array(v, Value::from),
+        UlogValue::Int64Array(v) => array(v, Value::from),
+        UlogValue::UInt64Array(v) => array(v, Value::from),
+        UlogValue::FloatArray(v) => array(v, float),
+        UlogValue::DoubleArray(v) => array(v, Value::from),
+        UlogValue::BoolArray(v) => array(v, Value::from),
+        UlogValue::CharArray(v) => Value::from(v.iter().collect::<String>()),
+        UlogValue::String(v) => Value::from(v.as_str()),
+    }
+}
+
+fn parameter_value(value: ParameterValue) -> Value {
+    match value {
+        ParameterValue::Float(v) => float(v),
+        ParameterValue::Int32(v) => Value::from(v),
+    }
+}
+
+fn column_values(column: &Column) -> Vec<Value> {
+    let element = |index: usize| match &column.data {
+        ColumnData::Int8(v) => Value::from(v[index]),
+        ColumnData::UInt8(v) => Value::from(v[index]),
+        ColumnData::Int16(v) => Value::from(v[index]),
+        ColumnData::UInt16(v) => Value::from(v[index]),
+        ColumnData::Int32(v) => Value::from(v[index]),
+        ColumnData::UInt32(v) => Value::from(v[index]),
+        ColumnData::Int64(v) => Value::from(v[index]),
+        ColumnData::UInt64(v) => Value::from(v[index]),
+        ColumnData::Float(v) => float(v[index]),
+        ColumnData::Double(v) => Value::from(v[index]),
+        ColumnData::Bool(v) => Value::from(v[index]),
+        ColumnData::Char(v) => Value::from(v[index].to_string()),
+        ColumnData::String(v) => Value::from(v[index].as_str()),
+    };
+    let stride = column.stride();
+    match (&column.data, column.array_len) {
+        (ColumnData::String(_), _) | (_, None) => (0..column.len()).map(element).collect(),
+        (_, Some(_)) => (0..column.len())
+            .map(|row| Value::Array((row * stride..(row + 1) * stride).map(element).collect()))
+            .collect(),
+    }
+}
+
+impl Ulog {
+    // The structure is:
+    //
+    // {
+    //   "header": { "version": 1, "timestamp": 0 },
+    //   "info": { "<name>": <value> },
+    //   "info_multiple": { "<name>": [<value>] },
+    //   "parameters": { "<name>": <number> },
+    //   "parameter_changes": [{ "timestamp": <us or null>, "name": ..., "value": ... }],
+    //   "formats": { "<name>": "<format string>" },
+    //   "subscriptions": [{ "msg_id": 0, "multi_id": 0, "message_name": ... }],
+    //   "logged_messages": [{ "timestamp": ..., "log_level": ..., "tag": <u16 or null>,
+    //                         "message": ... }],
+    //   "dropouts": [{ "duration_ms": ... }],
+    //   "datasets": [{ "message_name": ..., "multi_id": 0, "fields": { "<name>": [...] } }]
+    // }
+    //
+    // Array fields hold one array per sample, char arrays one string per sample.
+    // "datasets" is only present when `datasets` is true.
+    pub fn to_json(&self, datasets: bool) -> Result<Value, DecodeError> {
+        let info: Map<String, Value> = self
+            .info()
+            .iter()
+            .map(|(name, value)| (name.clone(), ulog_value(value)))
+            .collect();
+        let info_multiple: Map<String, Value> = self
+            .info_multiple_values()
+            .into_iter()
+            .map(|(name, values)| (name, values.iter().map(ulog_value).collect()))
+            .collect();
+        let parameters: Map<String, Value> = self
+            .parameters()
+            .into_iter()
+            .map(|(name, value)| (name, parameter_value(value)))
+            .collect();
+        let mut parameter_changes: Vec<(usize, Value)> = self
+            .parameter_changes()
+            .into_iter()
+            .flat_map(|(name, changes)| {
+                changes
+                    .into_iter()
+                    .filter(|change| !change.initial)
+                    .map(move |change| {
+                        let value = json!({
+                            "timestamp": change.timestamp,
+                            "name": name,
+                            "value": parameter_value(change.value),
+                        });
+                        (change.index, value)
+                    })
+            })
+            .collect();
+        parameter_changes.sort_by_key(|(index, _)| *index);
+        let formats: Map<String, Value> = self
+            .formats()?
+            .definitions
+            .into_iter()
+            .map(|(name, definition)| (name, Value::from(definition.to_string())))
+            .collect();
+
+        let mut subscriptions = Vec::new();
+        let mut logged_messages = Vec::new();
+        let mut dropouts = Vec::new();
+        for message in &self.data {
+            match message {
+                Message::AddLogged(add_logged) => subscriptions.push(json!({
+                    "msg_id": add_logged.msg_id,
+                    "multi_id": add_logged.multi_id,
+                    "message_name": &*add_logged.message_name,
+                })),
+                Message::Logging(logging) => logged_messages.push(json!({
+                    "timestamp": logging.timestamp,
+                    "log_level": logging.log_level,
+                    "tag": null,
+                    "message": logging.message,
+                })),
+                Message::LoggingTagged(logging) => logged_messages.push(json!({
+                    "timestamp": logging.timestamp,
+                    "log_level": logging.log_level,
+                    "tag": logging.tag,
+                    "message": logging.message,
+                })),
+                Message::Dropout(dropout) => {
+                    dropouts.push(json!({ "duration_ms": dropout.duration }))
+                }
+                _ => {}
+            }
+        }
+
+        let mut root = json!({
+            "header": {
+                "version": self.header.version,
+                "timestamp": self.header.timestamp,
+            },
+            "info": info,
+            "info_multiple": info_multiple,
+            "parameters": parameters,
+            "parameter_changes": parameter_changes
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>(),
+            "formats": formats,
+            "subscriptions": subscriptions,
+            "logged_messages": logged_messages,
+            "dropouts": dropouts,
+        });
+        if datasets {
+            let instances = self.topic_instances();
+            let mut values = Vec::with_capacity(instances.len());
+            for (message_name, multi_id) in instances {
+                let dataset = self.dataset(&message_name, multi_id)?;
+                let fields: Map<String, Value> = dataset
+                    .columns
+                    .iter()
+                    .filter(|column| !column.name.starts_with("_padding"))
+                    .map(|column| (column.name.clone(), Value::Array(column_values(column))))
+                    .collect();
+                values.push(json!({
+                    "message_name": message_name,
+                    "multi_id": multi_id,
+                    "fields": fields,
+                }));
+            }
+            root["datasets"] = Value::Array(values);
+        }
+        Ok(root)
+    }
+}
+
+pub(crate) fn sample_fields(sample: &DecodedData) -> Map<String, Value> {
+    sample
+        .fields()
+        .filter(|(name, _)| !name.starts_with("_padding"))
+        .map(|(name, value)| (name.to_string(), ulog_value(value)))
+        .collect()
+}
+
+// Writes one `{"topic", "multi_id", "timestamp", "fields"}` object per line, so samples
+// can be fed one at a time, for example while reading with `UlogReader`.
+#[derive(Debug)]
+pub struct JsonLinesWriter<W> {
+    writer: W,
+    samples: usize,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesWriter { writer, samples: 0 }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn write_sample(&mut self, sample: &DecodedData) -> io::Result<()> {
+        let line = json!({
+            "topic": sample.name(),
+            "multi_id": sample.multi_id,
+            "timestamp": sample.timestamp(),
+            "fields": sample_fields(sample),
+        });
+        serde_json::to_writer(&mut self.writer, &line)?;
+        self.writer.write_all(b"\n")?;
+        self.samples += 1;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl Ulog {
+    // Decodes and writes one sample at a time; returns the number of samples written.
+    pub fn write_jsonl(&self, writer: impl Write) -> Result<usize, ExportError> {
+        let mut decoder = Decoder::new(self.formats()?);
+        let mut lines = JsonLinesWriter::new(writer);
+        for message in &self.data {
+            match message {
+                Message::AddLogged(add_logged) => decoder.add_logged(add_logged)?,
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                Message::Data(data) => lines.write_sample(&decoder.decode(data)?)?,
+                _ => {}
+            }
+        }
+        lines.writer.flush()?;
+        Ok(lines.samples)
+    }
+}
@@ -1,93 +1,280 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use borrowed::borrowed_fields;
+use bytes::Bytes;
+use decode::check_payload;
+use intern::StringPool;
+use msg_ids::MsgIdSet;
 use nom::{
-    branch::alt,
     bytes::complete::{tag, take},
-    multi::many0,
+    combinator::rest,
+    multi::count,
     number::complete::{le_u16, le_u64, u8},
-    IResult,
+    Offset,
 };
 
-#[derive(Debug)]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        log::warn!($($arg)*);
+    };
+}
+
+mod anonymize;
+#[cfg(feature = "ndarray")]
+mod arrays;
+#[cfg(feature = "tokio")]
+mod async_reader;
+#[cfg(feature = "rayon")]
+mod batch;
+mod borrowed;
+mod checkpoint;
+mod chunked;
+mod compression;
+mod construct;
+#[cfg(feature = "polars")]
+mod dataframe;
+mod dataset;
+mod decode;
+mod edit;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod error;
+mod estimate;
+mod events;
+mod export;
+mod format;
+#[cfg(feature = "hdf5")]
+mod hdf5_export;
+mod index;
+mod info;
+mod intern;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "log")]
+mod logger;
+#[cfg(feature = "mcap")]
+mod mcap_export;
+mod merge;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod msg_ids;
+mod msg_type;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod parameter;
+#[cfg(feature = "parquet")]
+mod parquet_export;
+mod parser;
+mod progress;
+mod reader;
+#[cfg(feature = "arrow")]
+mod record_batch;
+mod repair;
+mod rewrite;
+mod schema;
+mod serialize;
+mod sidecar;
+mod split;
+mod stream;
+mod subscription;
+mod tag;
+mod topic;
+mod value;
+mod warning;
+mod writer;
+
+pub use anonymize::{AnonymizeOptions, PositionRedaction};
+#[cfg(feature = "arrow")]
+pub use arrow;
+#[cfg(feature = "tokio")]
+pub use async_reader::AsyncUlogReader;
+#[cfg(feature = "rayon")]
+pub use batch::{parse_many, BatchResult, FileError};
+pub use borrowed::{borrowed_message, borrowed_message_body, BorrowedMessage, BorrowedMessages};
+pub use checkpoint::{Checkpoint, CheckpointFlagBits, CheckpointSubscription};
+pub use chunked::{ChunkedError, DatasetSink, SkippedData};
+pub use compression::{
+    create_ulog_file, decompress, decompressing_reader, parse_ulog_file,
+    parse_ulog_file_with_options, read_ulog_file, CompressedWriter, Compression,
+};
+pub use dataset::{Column, ColumnData, Dataset, LazyDatasets};
+pub use decode::{decode_field, DecodeError, DecodedData, Decoder};
+#[cfg(feature = "encryption")]
+pub use encrypted::{decrypt, DecryptError, KeyHeader, UlogDecryptor, KEY_MAGIC};
+pub use error::{Limit, ParseError, ParseResult, UlogError, UlogErrorKind};
+pub use estimate::MemoryEstimate;
+pub use events::{Event, EventArgument, EventDefinition, EventEnum, EventMetadata};
+pub use export::{write_csv, CsvOptions, ExportError};
+pub use format::{
+    declaration, field, format_definition, Field, FieldType, FormatDefinition, FormatError,
+    Formats, Layout, LayoutField,
+};
+pub use index::{IndexEntry, TopicIndex, UlogIndex};
+#[cfg(feature = "json")]
+pub use json::JsonLinesWriter;
+#[cfg(feature = "log")]
+pub use logger::UlogLogger;
+pub use merge::{merge, MergeError};
+#[cfg(feature = "mmap")]
+pub use mmap::MappedUlog;
+pub use msg_type::MsgType;
+#[cfg(feature = "ndarray")]
+pub use ndarray;
+pub use options::{
+    CancellationToken, OrphanPolicy, ParseMode, ParseOptions, TopicFilter, Utf8Policy,
+    VersionPolicy,
+};
+pub use parameter::{DefaultParameters, DefaultTypes, ParameterChange, ParameterValue};
+#[cfg(feature = "parquet")]
+pub use parquet_export::write_parquet;
+pub use parser::{Feed, UlogParser};
+#[cfg(feature = "polars")]
+pub use polars;
+pub use progress::Progress;
+pub use reader::{TopicMessages, UlogReader};
+pub use repair::{repair, RepairReport};
+pub use rewrite::{Decimation, DownsampleError};
+pub use schema::{FieldSchema, Schema, TopicSchema};
+pub use split::SplitLimit;
+pub use stream::UlogStreamWriter;
+pub use subscription::{Subscription, Subscriptions};
+pub use tag::{LogTag, TagNames};
+pub use topic::{Sample, Topic, UlogField, UlogTopic};
+#[cfg(feature = "derive")]
+pub use ulogrs_derive::UlogTopic;
+pub use value::{string, value, UlogValue};
+pub use warning::{ParseWarning, ParseWarningKind};
+pub use writer::{UlogWriter, WriteError};
+
+#[derive(Debug, Clone)]
 pub struct Header {
     pub version: u8,
     pub timestamp: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct MessageHeader {
     pub msg_size: u16,
-    pub msg_type: u8,
+    pub msg_type: MsgType,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageFlagBits {
     pub header: MessageHeader,
     pub compat_flags: [u8; 8],
     pub incompat_flags: [u8; 8],
-    pub appended_offsets: [u8; 3],
+    pub appended_offsets: [u64; 3],
 }
 
-#[derive(Debug)]
+pub const MAGIC: [u8; 7] = [0x55, 0x4c, 0x6f, 0x67, 0x01, 0x12, 0x35];
+
+pub const ULOG_VERSION: u8 = 1;
+
+pub const INCOMPAT_FLAG0_DATA_APPENDED_MASK: u8 = 1 << 0;
+
+const PROGRESS_INTERVAL: usize = 1 << 20;
+
+impl MessageFlagBits {
+    pub fn has_data_appended(&self) -> bool {
+        self.incompat_flags[0] & INCOMPAT_FLAG0_DATA_APPENDED_MASK != 0
+    }
+
+    pub fn unknown_incompat_flags(&self) -> Option<[u8; 8]> {
+        let mut unknown = self.incompat_flags;
+        unknown[0] &= !INCOMPAT_FLAG0_DATA_APPENDED_MASK;
+        unknown.iter().any(|&flags| flags != 0).then_some(unknown)
+    }
+
+    pub fn appended_offsets(&self) -> impl Iterator<Item = u64> + '_ {
+        self.appended_offsets
+            .iter()
+            .copied()
+            .take_while(|&offset| offset != 0)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct MessageFormat {
     pub header: MessageHeader,
     pub format: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageInfo {
     pub header: MessageHeader,
     pub key_len: u8,
-    pub key: String,
+    pub key: Arc<str>,
     pub value: Vec<u8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageInfoMultiple {
     pub header: MessageHeader,
     pub is_continued: u8,
     pub key_len: u8,
-    pub key: String,
+    pub key: Arc<str>,
     pub value: Vec<u8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageParameter {
     pub header: MessageHeader,
     pub key_len: u8,
-    pub key: String,
+    pub key: Arc<str>,
     pub value: Vec<u8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageParameterDefault {
     pub header: MessageHeader,
-    pub default_types: u8,
+    pub default_types: DefaultTypes,
     pub key_len: u8,
-    pub key: String,
+    pub key: Arc<str>,
     pub value: Vec<u8>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageAddLogged {
     pub header: MessageHeader,
     pub multi_id: u8,
     pub msg_id: u16,
-    pub message_name: String,
+    pub message_name: Arc<str>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageRemoveLogged {
     pub header: MessageHeader,
     pub msg_id: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageData {
     pub header: MessageHeader,
     pub msg_id: u16,
-    pub data: Vec<u8>,
+    pub data: Bytes,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+pub struct MessageDataRef {
+    pub header: MessageHeader,
+    pub msg_id: u16,
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone)]
 pub struct MessageLogging {
     pub header: MessageHeader,
     pub log_level: u8,
@@ -95,7 +282,7 @@ pub struct MessageLogging {
     pub message: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageLoggingTagged {
     pub header: MessageHeader,
     pub log_level: u8,
@@ -104,19 +291,27 @@ pub struct MessageLoggingTagged {
     pub message: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MessageSync {
     pub header: MessageHeader,
-    pub sync_magic: u8,
+    pub sync_magic: [u8; 8],
 }
 
-#[derive(Debug)]
+pub const SYNC_MAGIC: [u8; 8] = [0x2f, 0x73, 0x13, 0x20, 0x25, 0x0c, 0xbb, 0x12];
+
+#[derive(Debug, Clone)]
 pub struct MessageDropout {
     pub header: MessageHeader,
     pub duration: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+pub struct MessageRaw {
+    pub header: MessageHeader,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
 pub enum Message {
     Format(MessageFormat),
     Info(MessageInfo),
@@ -126,254 +321,1309 @@ pub enum Message {
     AddLogged(MessageAddLogged),
     RemoveLogged(MessageRemoveLogged),
     Data(MessageData),
+    DataRef(MessageDataRef),
     Logging(MessageLogging),
     LoggingTagged(MessageLoggingTagged),
     Sync(MessageSync),
     Dropout(MessageDropout),
+    Raw(MessageRaw),
+}
+
+impl Message {
+    pub fn header(&self) -> MessageHeader {
+        match self {
+            Message::Format(MessageFormat { header, .. })
+            | Message::Info(MessageInfo { header, .. })
+            | Message::InfoMultiple(MessageInfoMultiple { header, .. })
+            | Message::Parameter(MessageParameter { header, .. })
+            | Message::ParameterDefault(MessageParameterDefault { header, .. })
+            | Message::AddLogged(MessageAddLogged { header, .. })
+            | Message::RemoveLogged(MessageRemoveLogged { header, .. })
+            | Message::Data(MessageData { header, .. })
+            | Message::DataRef(MessageDataRef { header, .. })
+            | Message::Logging(MessageLogging { header, .. })
+            | Message::LoggingTagged(MessageLoggingTagged { header, .. })
+            | Message::Sync(MessageSync { header, .. })
+            | Message::Dropout(MessageDropout { header, .. })
+            | Message::Raw(MessageRaw { header, .. }) => *header,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownMessage {
+    pub header: MessageHeader,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrailingBytes {
+    pub offset: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataSummary {
+    pub count: usize,
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
 }
 
 #[derive(Debug)]
 pub struct Ulog {
     pub header: Header,
     pub message_flag_bits: MessageFlagBits,
-    pub messages: Vec<Message>,
+    pub definitions: Vec<Message>,
+    pub data: Vec<Message>,
+    pub unknown_messages: Vec<UnknownMessage>,
+    pub warnings: Vec<ParseWarning>,
+    pub truncation: Option<UlogError>,
+    pub trailing_bytes: Option<TrailingBytes>,
+    pub orphan_data: Vec<MessageData>,
+    pub orphan_counts: BTreeMap<u16, usize>,
+    pub next_log_offset: Option<usize>,
+    pub cancelled: bool,
+    // Keyed by msg_id and the index in `data` of the AddLogged that subscribed it, as a
+    // msg_id can be reused after a RemoveLogged.
+    pub data_summary: BTreeMap<(u16, usize), DataSummary>,
 }
 
-pub fn header(input: &[u8]) -> IResult<&[u8], Header> {
-    let (input, _magic_number) = tag([0x55, 0x4c, 0x6f, 0x67, 0x01, 0x12, 0x35])(input)?;
+impl Ulog {
+    pub fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.definitions.iter().chain(&self.data)
+    }
+
+    pub fn time_range(&self) -> Option<(u64, u64)> {
+        let first = self
+            .data_summary
+            .values()
+            .filter_map(|summary| summary.first_timestamp)
+            .min()?;
+        let last = self
+            .data_summary
+            .values()
+            .filter_map(|summary| summary.last_timestamp)
+            .max()?;
+        Some((first, last))
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncation.is_some()
+    }
+
+    pub fn formats(&self) -> Result<Formats, FormatError> {
+        let mut formats = Formats::new();
+        for message in &self.definitions {
+            if let Message::Format(format) = message {
+                let definition = format.definition().map_err(|error| FormatError::Invalid {
+                    format: format.format.clone(),
+                    error,
+                })?;
+                formats.insert(definition);
+            }
+        }
+        Ok(formats)
+    }
+
+    pub fn schema(&self) -> Result<Schema, FormatError> {
+        Schema::new(&self.formats()?)
+    }
+
+    pub fn info(&self) -> BTreeMap<String, UlogValue> {
+        self.definitions
+            .iter()
+            .filter_map(|message| match message {
+                Message::Info(info) => Some((info.name()?.to_string(), info.typed_value()?)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn info_value(&self, name: &str) -> Option<UlogValue> {
+        self.messages().find_map(|message| match message {
+            Message::Info(info) if info.name() == Some(name) => info.typed_value(),
+            _ => None,
+        })
+    }
+
+    fn info_string(&self, name: &str) -> Option<String> {
+        match self.info_value(name)? {
+            UlogValue::String(value) => Some(value),
+            UlogValue::CharArray(value) => Some(value.into_iter().collect()),
+            _ => None,
+        }
+    }
+
+    pub fn sys_name(&self) -> Option<String> {
+        self.info_string("sys_name")
+    }
+
+    pub fn ver_hw(&self) -> Option<String> {
+        self.info_string("ver_hw")
+    }
+
+    pub fn ver_sw(&self) -> Option<String> {
+        self.info_string("ver_sw")
+    }
+
+    pub fn ver_sw_release(&self) -> Option<u32> {
+        match self.info_value("ver_sw_release")? {
+            UlogValue::UInt32(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn time_ref_utc(&self) -> Option<i32> {
+        match self.info_value("time_ref_utc")? {
+            UlogValue::Int32(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn info_multiple_parts(&self) -> HashMap<String, (Arc<str>, Vec<Vec<u8>>)> {
+        let mut parts: HashMap<String, (Arc<str>, Vec<Vec<u8>>)> = HashMap::new();
+        for message in self.messages() {
+            let Message::InfoMultiple(info) = message else {
+                continue;
+            };
+            let Some(name) = info.name() else {
+                continue;
+            };
+            let (_, values) = parts
+                .entry(name.to_string())
+                .or_insert_with(|| (info.key.clone(), Vec::new()));
+            match values.last_mut() {
+                Some(last) if info.is_continued != 0 => last.extend_from_slice(&info.value),
+                _ => values.push(info.value.clone()),
+            }
+        }
+        parts
+    }
+
+    pub fn info_multiple(&self) -> HashMap<String, Vec<Vec<u8>>> {
+        self.info_multiple_parts()
+            .into_iter()
+            .map(|(name, (_, values))| (name, values))
+            .collect()
+    }
+
+    pub fn info_multiple_values(&self) -> HashMap<String, Vec<UlogValue>> {
+        self.info_multiple_parts()
+            .into_iter()
+            .map(|(name, (key, values))| {
+                let values = values
+                    .iter()
+                    .filter_map(|bytes| info::concatenated_value(&key, bytes))
+                    .collect();
+                (name, values)
+            })
+            .collect()
+    }
+
+    pub fn parameters(&self) -> BTreeMap<String, ParameterValue> {
+        self.definitions
+            .iter()
+            .filter_map(|message| match message {
+                Message::Parameter(parameter) => {
+                    Some((parameter.name()?.to_string(), parameter.typed_value()?))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn parameter_changes(&self) -> BTreeMap<String, Vec<ParameterChange>> {
+        let mut decoder = Decoder::new(self.formats().unwrap_or_default());
+        let mut timestamp = None;
+        let mut changes: BTreeMap<String, Vec<ParameterChange>> = BTreeMap::new();
+        for (index, message) in self.messages().enumerate() {
+            match message {
+                Message::AddLogged(add_logged) => {
+                    let _ = decoder.add_logged(add_logged);
+                }
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                Message::Data(data) => {
+                    let data_timestamp = decoder
+                        .layout(data.msg_id)
+                        .and_then(|layout| layout.timestamp_field())
+                        .and_then(|field| decode_field(data, field).ok())
+                        .and_then(|value| value.as_u64());
+                    timestamp = data_timestamp.or(timestamp);
+                }
+                Message::Logging(logging) => timestamp = Some(logging.timestamp),
+                Message::LoggingTagged(logging) => timestamp = Some(logging.timestamp),
+                Message::Parameter(parameter) => {
+                    let (Some(name), Some(value)) = (parameter.name(), parameter.typed_value())
+                    else {
+                        continue;
+                    };
+                    changes
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(ParameterChange {
+                            index,
+                            timestamp,
+                            value,
+                            initial: index < self.definitions.len(),
+                        });
+                }
+                _ => {}
+            }
+        }
+        changes
+    }
+
+    pub fn default_parameters(&self) -> DefaultParameters {
+        let mut defaults = DefaultParameters::default();
+        for message in self.messages() {
+            if let Message::ParameterDefault(default) = message {
+                defaults.insert(default);
+            }
+        }
+        defaults
+    }
+
+    pub fn non_default_parameters(&self) -> BTreeMap<String, (ParameterValue, ParameterValue)> {
+        self.default_parameters().differing(&self.parameters())
+    }
+
+    pub fn parameter_defaults(&self, name: &str) -> Vec<(DefaultTypes, ParameterValue)> {
+        self.messages()
+            .filter_map(|message| match message {
+                Message::ParameterDefault(default) if default.name() == Some(name) => {
+                    Some((default.default_types, default.typed_value()?))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn parameter_default(
+        &self,
+        name: &str,
+        default_types: DefaultTypes,
+    ) -> Option<ParameterValue> {
+        self.parameter_defaults(name)
+            .into_iter()
+            .rev()
+            .find(|(types, _)| types.contains(default_types))
+            .map(|(_, value)| value)
+    }
+
+    fn for_each_data(
+        &self,
+        message_name: &str,
+        multi_id: u8,
+        mut f: impl FnMut(&Decoder, &MessageData) -> Result<(), DecodeError>,
+    ) -> Result<(), DecodeError> {
+        let mut decoder = Decoder::new(self.formats()?);
+        for message in &self.data {
+            match message {
+                Message::AddLogged(add_logged)
+                    if *add_logged.message_name == *message_name
+                        && add_logged.multi_id == multi_id =>
+                {
+                    decoder.add_logged(add_logged)?
+                }
+                Message::AddLogged(add_logged) => decoder.remove_logged(add_logged.msg_id),
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                Message::Data(data) if decoder.is_subscribed(data.msg_id) => f(&decoder, data)?,
+                Message::DataRef(data) if decoder.is_subscribed(data.msg_id) => {
+                    return Err(DecodeError::UnresolvedDataRef(data.msg_id))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_dataset(
+        &self,
+        message_name: &str,
+        multi_id: u8,
+    ) -> Result<Vec<DecodedData>, DecodeError> {
+        let mut samples = Vec::new();
+        self.for_each_data(message_name, multi_id, |decoder, data| {
+            samples.push(decoder.decode(data)?);
+            Ok(())
+        })?;
+        Ok(samples)
+    }
+
+    pub fn dataset(&self, message_name: &str, multi_id: u8) -> Result<Dataset, DecodeError> {
+        let layout = self.formats()?.layout(message_name)?;
+        let mut dataset = Dataset::new(&layout, multi_id);
+        self.for_each_data(message_name, multi_id, |_, data| {
+            check_payload(data.msg_id, &layout, &data.data)?;
+            dataset.push_payload(&layout, &data.data);
+            Ok(())
+        })?;
+        Ok(dataset)
+    }
+
+    pub fn projected_dataset(
+        &self,
+        message_name: &str,
+        multi_id: u8,
+        fields: &[&str],
+    ) -> Result<Dataset, DecodeError> {
+        let layout = self
+            .formats()?
+            .layout(message_name)?
+            .project(fields)
+            .map_err(|name| DecodeError::UnknownField(name.to_string()))?;
+        let mut dataset = Dataset::new(&layout, multi_id);
+        self.for_each_data(message_name, multi_id, |_, data| {
+            check_payload(data.msg_id, &layout, &data.data)?;
+            dataset.push_payload(&layout, &data.data);
+            Ok(())
+        })?;
+        Ok(dataset)
+    }
+
+    pub fn lazy(&self) -> LazyDatasets<'_> {
+        LazyDatasets::new(self)
+    }
+
+    pub fn instances(&self, message_name: &str) -> Vec<u8> {
+        let mut instances: Vec<u8> = self
+            .data
+            .iter()
+            .filter_map(|message| match message {
+                Message::AddLogged(add_logged) if *add_logged.message_name == *message_name => {
+                    Some(add_logged.multi_id)
+                }
+                _ => None,
+            })
+            .collect();
+        instances.sort_unstable();
+        instances.dedup();
+        instances
+    }
+
+    pub fn datasets(&self, message_name: &str) -> Result<Vec<Dataset>, DecodeError> {
+        self.instances(message_name)
+            .into_iter()
+            .map(|multi_id| self.dataset(message_name, multi_id))
+            .collect()
+    }
+
+    pub fn stacked_dataset(&self, message_name: &str) -> Result<Option<Dataset>, DecodeError> {
+        Ok(Dataset::stack(&self.datasets(message_name)?))
+    }
+
+    pub fn timestamps(&self, message_name: &str, multi_id: u8) -> Result<Vec<u64>, DecodeError> {
+        let layout = self.formats()?.layout(message_name)?;
+        let Some(field) = layout.timestamp_field() else {
+            return Ok(Vec::new());
+        };
+        let mut timestamps = Vec::new();
+        self.for_each_data(message_name, multi_id, |_, data| {
+            if let Some(timestamp) = decode_field(data, field)?.as_u64() {
+                timestamps.push(timestamp);
+            }
+            Ok(())
+        })?;
+        Ok(timestamps)
+    }
+
+    pub fn events(&self) -> Result<Vec<Event>, DecodeError> {
+        let mut events = Vec::new();
+        self.for_each_data("event", 0, |decoder, data| {
+            events.extend(Event::from_sample(&decoder.decode(data)?));
+            Ok(())
+        })?;
+        Ok(events)
+    }
+
+    pub fn tagged_logging(&self, tag_names: &TagNames, name: &str) -> Vec<&MessageLoggingTagged> {
+        self.data
+            .iter()
+            .filter_map(|message| match message {
+                Message::LoggingTagged(logging) if tag_names.name(logging.tag) == Some(name) => {
+                    Some(logging)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn tagged_logging_by_tag(
+        &self,
+        tag_names: &TagNames,
+    ) -> BTreeMap<String, Vec<&MessageLoggingTagged>> {
+        let mut groups: BTreeMap<String, Vec<&MessageLoggingTagged>> = BTreeMap::new();
+        for message in &self.data {
+            if let Message::LoggingTagged(logging) = message {
+                let name = match tag_names.name(logging.tag) {
+                    Some(name) => name.to_string(),
+                    None => logging.tag.to_string(),
+                };
+                groups.entry(name).or_default().push(logging);
+            }
+        }
+        groups
+    }
+
+    pub fn subscriptions(&self) -> Subscriptions {
+        let mut subscriptions = Subscriptions::new();
+        for (index, message) in self.data.iter().enumerate() {
+            match message {
+                Message::AddLogged(add_logged) => subscriptions.add_logged(add_logged, index),
+                Message::RemoveLogged(remove_logged) => {
+                    subscriptions.remove_logged(remove_logged.msg_id, index)
+                }
+                _ => {}
+            }
+        }
+        subscriptions
+    }
+
+    pub fn decoded_data(&self) -> Result<Vec<DecodedData>, DecodeError> {
+        let mut decoder = Decoder::new(self.formats()?);
+        let mut decoded = Vec::new();
+        for message in &self.data {
+            match message {
+                Message::AddLogged(add_logged) => decoder.add_logged(add_logged)?,
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                Message::Data(data) => decoded.push(decoder.decode(data)?),
+                Message::DataRef(data) => return Err(DecodeError::UnresolvedDataRef(data.msg_id)),
+                _ => {}
+            }
+        }
+        Ok(decoded)
+    }
+}
+
+pub fn header(input: &[u8]) -> ParseResult<'_, Header> {
+    let (input, _magic_number) = tag(MAGIC)(input).map_err(|err: nom::Err<ParseError>| {
+        err.map(|_| ParseError::new(input, UlogErrorKind::BadMagic))
+    })?;
     let (input, version) = u8(input)?;
     let (input, timestamp) = le_u64(input)?;
     Ok((input, Header { version, timestamp }))
 }
 
-pub fn message_header(input: &[u8], msg_type: u8) -> IResult<&[u8], MessageHeader> {
+pub fn message_header(input: &[u8]) -> ParseResult<'_, MessageHeader> {
     let (input, msg_size) = le_u16(input)?;
-    let (input, msg_type) = tag([msg_type])(input)?;
+    let (input, msg_type) = u8(input)?;
     Ok((
         input,
         MessageHeader {
             msg_size,
-            msg_type: msg_type[0],
+            msg_type: msg_type.into(),
         },
     ))
 }
 
-pub fn message_flag_bits(input: &[u8]) -> IResult<&[u8], MessageFlagBits> {
-    let (input, header) = message_header(input, b'B')?;
+pub(crate) fn utf8_string<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> Result<String, nom::Err<ParseError<'a>>> {
+    match options.utf8_policy {
+        Utf8Policy::Lossy => Ok(String::from_utf8_lossy(input).into_owned()),
+        Utf8Policy::Raw | Utf8Policy::Error => String::from_utf8(input.to_vec()).map_err(|err| {
+            let position = &input[err.utf8_error().valid_up_to()..];
+            nom::Err::Failure(ParseError::new(position, UlogErrorKind::InvalidUtf8(err)))
+        }),
+    }
+}
+
+pub(crate) fn too_short(
+    err: nom::Err<ParseError<'_>>,
+    header: MessageHeader,
+) -> nom::Err<ParseError<'_>> {
+    match err {
+        nom::Err::Error(ParseError {
+            input,
+            kind: UlogErrorKind::Truncated,
+            ..
+        }) => nom::Err::Failure(ParseError::new(
+            input,
+            UlogErrorKind::MessageTooShort {
+                msg_type: header.msg_type,
+                msg_size: header.msg_size,
+            },
+        )),
+        err => err,
+    }
+}
+
+pub fn message_flag_bits<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> ParseResult<'a, MessageFlagBits> {
+    let start = input;
+    let (input, header) = message_header(input)?;
+    if header.msg_type != MsgType::FlagBits {
+        return Err(nom::Err::Error(ParseError::new(
+            input,
+            UlogErrorKind::MalformedMessageHeader {
+                expected: MsgType::FlagBits,
+                found: header.msg_type,
+            },
+        )));
+    }
     let (input, message_input) = take(header.msg_size)(input)?;
-    let (message_input, compat_flags) = take(8usize)(message_input)?;
-    let (message_input, incompat_flags) = take(8usize)(message_input)?;
-    let (_message_input, appended_offsets) = take(3usize)(message_input)?;
-    Ok((
-        input,
-        MessageFlagBits {
-            header,
-            compat_flags: compat_flags.try_into().unwrap(),
-            incompat_flags: incompat_flags.try_into().unwrap(),
-            appended_offsets: appended_offsets.try_into().unwrap(),
-        },
-    ))
+    let (message_input, compat_flags) =
+        take(8usize)(message_input).map_err(|err| too_short(err, header))?;
+    let (message_input, incompat_flags) =
+        take(8usize)(message_input).map_err(|err| too_short(err, header))?;
+    let (_message_input, appended_offsets) =
+        count(le_u64, 3)(message_input).map_err(|err| too_short(err, header))?;
+    let message_flag_bits = MessageFlagBits {
+        header,
+        compat_flags: compat_flags.try_into().unwrap(),
+        incompat_flags: incompat_flags.try_into().unwrap(),
+        appended_offsets: appended_offsets.try_into().unwrap(),
+    };
+    if let Some(unknown) = message_flag_bits.unknown_incompat_flags() {
+        if !options.allow_unknown_incompat_flags {
+            return Err(nom::Err::Failure(ParseError::new(
+                start,
+                UlogErrorKind::UnknownIncompatFlags(unknown),
+            )));
+        }
+    }
+    Ok((input, message_flag_bits))
 }
 
-pub fn message_format(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'F')?;
-    let (input, format) = take(header.msg_size)(input)?;
-    Ok((
+// The owned parsers convert the borrowed ones, so each message is parsed in one place.
+fn owned<'a>(result: ParseResult<'a, BorrowedMessage<'a>>) -> ParseResult<'a, Message> {
+    result.map(|(input, message)| (input, message.into_owned()))
+}
+
+pub fn message_format<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(input, MsgType::Format, header, options))
+}
+
+pub fn message_info<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(input, MsgType::Info, header, options))
+}
+
+pub fn message_info_multiple<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(
         input,
-        Message::Format(MessageFormat {
-            header,
-            format: String::from_utf8(format.to_vec()).unwrap(),
-        }),
+        MsgType::InfoMultiple,
+        header,
+        options,
     ))
 }
 
-pub fn message_info(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'I')?;
-    let (input, key_len) = u8(input)?;
-    let (input, key) = take(key_len)(input)?;
-    let (input, value) = take(header.msg_size - 1 - key_len as u16)(input)?;
-    Ok((
+pub fn message_parameter<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(input, MsgType::Parameter, header, options))
+}
+
+pub fn message_parameter_default<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(
         input,
-        Message::Info(MessageInfo {
-            header,
-            key_len,
-            key: String::from_utf8(key.to_vec()).unwrap(),
-            value: value.to_vec(),
-        }),
+        MsgType::ParameterDefault,
+        header,
+        options,
     ))
 }
 
-pub fn message_info_multiple(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'M')?;
-    let (input, is_continued) = u8(input)?;
-    let (input, key_len) = u8(input)?;
-    let (input, key) = take(key_len)(input)?;
-    let (input, value) = take(header.msg_size - 2 - key_len as u16)(input)?;
-    Ok((
+pub fn message_add_logged<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(input, MsgType::AddLogged, header, options))
+}
+
+pub fn message_remove_logged(input: &[u8], header: MessageHeader) -> ParseResult<'_, Message> {
+    owned(borrowed_fields(
         input,
-        Message::InfoMultiple(MessageInfoMultiple {
-            header,
-            is_continued,
-            key_len,
-            key: String::from_utf8(key.to_vec()).unwrap(),
-            value: value.to_vec(),
-        }),
+        MsgType::RemoveLogged,
+        header,
+        &ParseOptions::default(),
     ))
 }
 
-pub fn message_parameter(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'P')?;
-    let (input, key_len) = u8(input)?;
-    let (input, key) = take(key_len)(input)?;
-    let (input, value) = take(header.msg_size - 1 - key_len as u16)(input)?;
-    Ok((
+pub fn message_data(input: &[u8], header: MessageHeader) -> ParseResult<'_, Message> {
+    owned(borrowed_fields(
         input,
-        Message::Parameter(MessageParameter {
-            header,
-            key_len,
-            key: String::from_utf8(key.to_vec()).unwrap(),
-            value: value.to_vec(),
-        }),
+        MsgType::Data,
+        header,
+        &ParseOptions::default(),
     ))
 }
 
-pub fn message_parameter_default(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'Q')?;
-    let (input, default_types) = u8(input)?;
-    let (input, key_len) = u8(input)?;
-    let (input, key) = take(key_len)(input)?;
-    let (input, value) = take(header.msg_size - 2 - key_len as u16)(input)?;
-    Ok((
+pub fn message_logging<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(input, MsgType::Logging, header, options))
+}
+
+pub fn message_logging_tagged<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_fields(
         input,
-        Message::ParameterDefault(MessageParameterDefault {
-            header,
-            default_types,
-            key_len,
-            key: String::from_utf8(key.to_vec()).unwrap(),
-            value: value.to_vec(),
-        }),
+        MsgType::LoggingTagged,
+        header,
+        options,
     ))
 }
 
-pub fn message_add_logged(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'A')?;
-    let (input, multi_id) = u8(input)?;
-    let (input, msg_id) = le_u16(input)?;
-    let (input, message_name) = take(header.msg_size - 3)(input)?;
-    Ok((
+pub fn message_sync(input: &[u8], header: MessageHeader) -> ParseResult<'_, Message> {
+    owned(borrowed_fields(
         input,
-        Message::AddLogged(MessageAddLogged {
-            header,
-            multi_id,
-            msg_id,
-            message_name: String::from_utf8(message_name.to_vec()).unwrap(),
-        }),
+        MsgType::Sync,
+        header,
+        &ParseOptions::default(),
     ))
 }
 
-pub fn message_remove_logged(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'R')?;
-    let (input, msg_id) = le_u16(input)?;
-    Ok((
+pub fn message_dropout(input: &[u8], header: MessageHeader) -> ParseResult<'_, Message> {
+    owned(borrowed_fields(
         input,
-        Message::RemoveLogged(MessageRemoveLogged { header, msg_id }),
+        MsgType::Dropout,
+        header,
+        &ParseOptions::default(),
     ))
 }
 
-pub fn message_data(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'D')?;
-    let (input, msg_id) = le_u16(input)?;
-    let (input, data) = take(header.msg_size - 2)(input)?;
+pub fn message_raw(input: &[u8], header: MessageHeader) -> ParseResult<'_, Message> {
+    let (input, data) = rest(input)?;
     Ok((
         input,
-        Message::Data(MessageData {
+        Message::Raw(MessageRaw {
             header,
-            msg_id,
             data: data.to_vec(),
         }),
     ))
 }
 
-pub fn message_logging(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'L')?;
-    let (input, log_level) = u8(input)?;
-    let (input, timestamp) = le_u64(input)?;
-    let (input, message) = take(header.msg_size - 9)(input)?;
-    Ok((
-        input,
-        Message::Logging(MessageLogging {
-            header,
-            log_level,
-            timestamp,
-            message: String::from_utf8(message.to_vec()).unwrap(),
-        }),
-    ))
+pub fn message_body<'a>(
+    input: &'a [u8],
+    header: MessageHeader,
+    options: &ParseOptions,
+) -> ParseResult<'a, Message> {
+    owned(borrowed_message_body(input, header, options))
 }
 
-pub fn message_logging_tagged(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'C')?;
-    let (input, log_level) = u8(input)?;
-    let (input, tag) = le_u16(input)?;
-    let (input, timestamp) = le_u64(input)?;
-    let (input, message) = take(header.msg_size - 11)(input)?;
-    Ok((
-        input,
-        Message::LoggingTagged(MessageLoggingTagged {
-            header,
-            log_level,
-            tag,
-            timestamp,
-            message: String::from_utf8(message.to_vec()).unwrap(),
-        }),
-    ))
+pub fn message_frame(input: &[u8]) -> ParseResult<'_, (MessageHeader, &[u8])> {
+    let (input, header) = message_header(input)?;
+    let (input, body) = take(header.msg_size)(input)?;
+    Ok((input, (header, body)))
 }
 
-pub fn message_sync(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'S')?;
-    let (input, sync_magic) = u8(input)?;
-    Ok((input, Message::Sync(MessageSync { header, sync_magic })))
-}
-
-pub fn message_dropout(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, header) = message_header(input, b'O')?;
-    let (input, duration) = le_u16(input)?;
-    Ok((input, Message::Dropout(MessageDropout { header, duration })))
-}
-
-pub fn message(input: &[u8]) -> IResult<&[u8], Message> {
-    let (input, message) = alt((
-        message_format,
-        message_info,
-        message_info_multiple,
-        message_parameter,
-        message_parameter_default,
-        message_add_logged,
-        message_remove_logged,
-        message_data,
-        message_logging,
-        message_logging_tagged,
-        message_sync,
-        message_dropout,
-    ))(input)?;
+pub fn message<'a>(input: &'a [u8], options: &ParseOptions) -> ParseResult<'a, Message> {
+    let (input, (header, body)) = message_frame(input)?;
+    let (_, message) = message_body(body, header, options)?;
     Ok((input, message))
 }
 
-pub fn ulog(input: &[u8]) -> IResult<&[u8], Ulog> {
-    let (input, header) = header(input)?;
-    let (input, message_flag_bits) = message_flag_bits(input)?;
-    let (_, messages) = many0(message)(input)?;
+pub fn find_next_sync(input: &[u8], offset: usize) -> Option<usize> {
+    let position = memchr::memmem::find(input.get(offset..)?, &SYNC_MAGIC)?;
+    Some(offset + position)
+}
 
-    Ok((
-        &[],
-        Ulog {
+pub(crate) fn find_sync(input: &[u8]) -> Option<usize> {
+    let position = find_next_sync(input, 0)?;
+    match position.checked_sub(3) {
+        Some(start) if input[start..position] == [8, 0, b'S'] => Some(start),
+        _ => Some(position + SYNC_MAGIC.len()),
+    }
+}
+
+pub(crate) fn fixed_msg_size(msg_type: MsgType) -> Option<u16> {
+    match msg_type {
+        MsgType::FlagBits => Some(40),
+        MsgType::RemoveLogged | MsgType::Dropout => Some(2),
+        MsgType::Sync => Some(8),
+        _ => None,
+    }
+}
+
+pub(crate) fn starts_data_section(msg_type: MsgType) -> bool {
+    matches!(
+        msg_type,
+        MsgType::AddLogged
+            | MsgType::RemoveLogged
+            | MsgType::Data
+            | MsgType::Logging
+            | MsgType::LoggingTagged
+            | MsgType::Sync
+            | MsgType::Dropout
+    )
+}
+
+pub(crate) fn is_corrupt_msg_type(msg_type: MsgType) -> bool {
+    !u8::from(msg_type).is_ascii_alphabetic()
+}
+
+struct Context<'a, 'o> {
+    start: &'a [u8],
+    bytes: Option<Bytes>,
+    options: &'o ParseOptions,
+    validating_options: ParseOptions,
+    ulog: Ulog,
+    message_index: usize,
+    subscribed: MsgIdSet,
+    filtered: MsgIdSet,
+    strings: StringPool,
+    progress: Option<&'o mut dyn FnMut(Progress)>,
+    next_progress: usize,
+    formats: Option<Formats>,
+    summary_keys: HashMap<u16, (usize, Option<usize>)>,
+}
+
+impl<'a> Context<'a, '_> {
+    fn warn(&mut self, input: &'a [u8], kind: ParseWarningKind) {
+        let warning = ParseWarning {
+            kind,
+            offset: self.start.offset(input),
+            message_index: Some(self.message_index),
+        };
+        warn!("{}", warning);
+        self.ulog.warnings.push(warning);
+    }
+
+    fn report_progress(&mut self, offset: usize) {
+        let Some(progress) = &mut self.progress else {
+            return;
+        };
+        if offset < self.next_progress {
+            return;
+        }
+        self.next_progress = offset + PROGRESS_INTERVAL;
+        progress(Progress {
+            bytes: offset,
+            total_bytes: Some(self.start.len()),
+            messages: self.message_index,
+        });
+    }
+
+    fn recover(&mut self, err: nom::Err<ParseError<'a>>) -> Result<(), nom::Err<ParseError<'a>>> {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err)
+                if self.options.mode == ParseMode::Lenient =>
+            {
+                self.warn(err.input, ParseWarningKind::Recovered(err.kind));
+                Ok(())
+            }
+            err => Err(err.map(|err| err.at_message(self.message_index))),
+        }
+    }
+
+    fn check_limits(
+        &self,
+        input: &'a [u8],
+        rest: &'a [u8],
+        header: MessageHeader,
+    ) -> Result<(), nom::Err<ParseError<'a>>> {
+        let options = self.options;
+        let limit = options
+            .max_messages
+            .filter(|&max| self.message_index >= max)
+            .map(Limit::Messages)
+            .or_else(|| {
+                options
+                    .max_message_size
+                    .filter(|&max| header.msg_size > max)
+                    .map(Limit::MessageSize)
+            })
+            .or_else(|| {
+                options
+                    .max_bytes
+                    .filter(|&max| self.start.offset(rest) > max)
+                    .map(Limit::Bytes)
+            });
+        match limit {
+            Some(limit) => Err(nom::Err::Failure(
+                ParseError::new(input, UlogErrorKind::LimitExceeded(limit))
+                    .at_message(self.message_index),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn truncate(&mut self, input: &'a [u8]) -> Result<(), nom::Err<ParseError<'a>>> {
+        let err = nom::Err::Error(
+            ParseError::new(input, UlogErrorKind::Truncated).at_message(self.message_index),
+        );
+        if self.options.mode == ParseMode::Strict {
+            return Err(err);
+        }
+        let truncation = UlogError::new(err, self.start);
+        warn!("{}", truncation);
+        self.ulog.truncation = Some(truncation);
+        Ok(())
+    }
+
+    fn resync(&mut self, input: &'a [u8]) -> Option<&'a [u8]> {
+        if self.options.mode == ParseMode::Strict {
+            return None;
+        }
+        let skipped_bytes = find_sync(&input[1..])? + 1;
+        self.warn(input, ParseWarningKind::SyncLost { skipped_bytes });
+        debug!(
+            "resuming at offset {}",
+            self.start.offset(input) + skipped_bytes
+        );
+        Some(&input[skipped_bytes..])
+    }
+
+    fn appended_boundaries(
+        &mut self,
+        flag_bits_input: &'a [u8],
+        data_offset: usize,
+    ) -> Result<Vec<usize>, nom::Err<ParseError<'a>>> {
+        let mut boundaries = Vec::new();
+        if !self.ulog.message_flag_bits.has_data_appended() {
+            return Ok(boundaries);
+        }
+        let mut previous = data_offset;
+        let offsets: Vec<u64> = self.ulog.message_flag_bits.appended_offsets().collect();
+        for offset in offsets {
+            match usize::try_from(offset) {
+                Ok(boundary) if boundary >= previous && boundary <= self.start.len() => {
+                    boundaries.push(boundary);
+                    previous = boundary;
+                }
+                _ => {
+                    self.recover(nom::Err::Error(ParseError::new(
+                        flag_bits_input,
+                        UlogErrorKind::InvalidAppendedOffset(offset),
+                    )))?;
+                    break;
+                }
+            }
+        }
+        Ok(boundaries)
+    }
+
+    fn push(
+        &mut self,
+        body: &'a [u8],
+        header: MessageHeader,
+        mut message: Message,
+    ) -> Result<(), nom::Err<ParseError<'a>>> {
+        self.strings.intern_message(&mut message);
+        match &message {
+            Message::AddLogged(add_logged) => {
+                if self.options.metadata_only {
+                    let formats = self
+                        .formats
+                        .get_or_insert_with(|| self.ulog.formats().unwrap_or_default());
+                    let timestamp_offset = formats
+                        .layout(&add_logged.message_name)
+                        .ok()
+                        .and_then(|layout| Some(layout.timestamp_field()?.offset));
+                    self.summary_keys.insert(
+                        add_logged.msg_id,
+                        (self.ulog.data.len(), timestamp_offset),
+                    );
+                }
+                self.subscribed.insert(add_logged.msg_id);
+                if self.options.topic_filter.allows(&add_logged.message_name) {
+                    self.filtered.remove(add_logged.msg_id);
+                } else {
+                    self.filtered.insert(add_logged.msg_id);
+                }
+            }
+            Message::RemoveLogged(remove_logged) => {
+                self.subscribed.remove(remove_logged.msg_id);
+                self.filtered.remove(remove_logged.msg_id);
+            }
+            Message::Data(data) if !self.subscribed.contains(data.msg_id) => {
+                let msg_id = data.msg_id;
+                *self.ulog.orphan_counts.entry(msg_id).or_default() += 1;
+                match self.options.orphan_policy {
+                    OrphanPolicy::Keep => {
+                        if let Message::Data(data) = message {
+                            self.ulog.orphan_data.push(data);
+                        }
+                    }
+                    OrphanPolicy::Drop => {}
+                    OrphanPolicy::Error => {
+                        return Err(nom::Err::Failure(
+                            ParseError::new(body, UlogErrorKind::OrphanData(msg_id))
+                                .at_message(self.message_index),
+                        ))
+                    }
+                }
+                return Ok(());
+            }
+            Message::DataRef(data) if !self.subscribed.contains(data.msg_id) => {
+                let data = MessageData {
+                    header,
+                    msg_id: data.msg_id,
+                    data: self.payload(&body[2..]),
+                };
+                return self.push(body, header, Message::Data(data));
+            }
+            _ => {}
+        }
+        if self.ulog.data.is_empty() && !starts_data_section(header.msg_type) {
+            self.ulog.definitions.push(message);
+        } else {
+            self.ulog.data.push(message);
+        }
+        Ok(())
+    }
+
+    fn payload(&self, payload: &[u8]) -> Bytes {
+        let offset = self.start.offset(payload);
+        match &self.bytes {
+            Some(bytes) => bytes.slice(offset..offset + payload.len()),
+            None => Bytes::copy_from_slice(payload),
+        }
+    }
+
+    fn data(&self, body: &'a [u8], header: MessageHeader) -> ParseResult<'a, Message> {
+        let (payload, msg_id) = le_u16(body).map_err(|err| too_short(err, header))?;
+        Ok((
+            &payload[payload.len()..],
+            Message::Data(MessageData {
+                header,
+                msg_id,
+                data: self.payload(payload),
+            }),
+        ))
+    }
+
+    fn data_ref(&self, body: &'a [u8], header: MessageHeader) -> ParseResult<'a, Message> {
+        let (payload, msg_id) = le_u16(body).map_err(|err| too_short(err, header))?;
+        Ok((
+            &payload[payload.len()..],
+            Message::DataRef(MessageDataRef {
+                header,
+                msg_id,
+                offset: self.start.offset(payload),
+                len: payload.len(),
+            }),
+        ))
+    }
+
+    fn summarize(&mut self, body: &[u8]) {
+        let Ok((payload, msg_id)) = le_u16::<_, ParseError>(body) else {
+            return;
+        };
+        if !self.subscribed.contains(msg_id) {
+            *self.ulog.orphan_counts.entry(msg_id).or_default() += 1;
+            return;
+        }
+        let Some(&(added, timestamp_offset)) = self.summary_keys.get(&msg_id) else {
+            return;
+        };
+        let timestamp = timestamp_offset
+            .and_then(|offset| payload.get(offset..offset + 8))
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        let summary = self.ulog.data_summary.entry((msg_id, added)).or_default();
+        summary.count += 1;
+        if let Some(timestamp) = timestamp {
+            summary.first_timestamp.get_or_insert(timestamp);
+            summary.last_timestamp = Some(timestamp);
+        }
+    }
+
+    fn is_filtered(&self, header: MessageHeader, body: &[u8]) -> bool {
+        header.msg_type == MsgType::Data
+            && !self.filtered.is_empty()
+            && le_u16::<_, ParseError>(body).is_ok_and(|(_, msg_id)| self.filtered.contains(msg_id))
+    }
+
+    fn messages(
+        &mut self,
+        mut input: &'a [u8],
+        appended_boundary: bool,
+    ) -> Result<&'a [u8], nom::Err<ParseError<'a>>> {
+        while !input.is_empty() {
+            let offset = self.start.offset(input);
+            self.report_progress(offset);
+            if self.options.is_cancelled() {
+                debug!("parsing cancelled at offset {}", offset);
+                self.ulog.cancelled = true;
+                break;
+            }
+            if input.starts_with(&MAGIC) {
+                debug!("found the header of another log at offset {}", offset);
+                self.ulog.next_log_offset = Some(offset);
+                break;
+            }
+            let (rest, (header, body)) = match message_frame(input) {
+                Ok(result) => result,
+                // Appended data may start in the middle of a message cut off by the crash.
+                Err(_) if appended_boundary => break,
+                Err(_) => match self.resync(input) {
+                    Some(rest) => {
+                        input = rest;
+                        continue;
+                    }
+                    None => {
+                        self.truncate(input)?;
+                        break;
+                    }
+                },
+            };
+            self.check_limits(input, rest, header)?;
+            if is_corrupt_msg_type(header.msg_type) {
+                if let Some(rest) = self.resync(input) {
+                    input = rest;
+                    continue;
+                }
+                self.recover(nom::Err::Failure(ParseError::new(
+                    &input[2..],
+                    UlogErrorKind::UnknownMessageType(header.msg_type.into()),
+                )))?;
+                break;
+            }
+            if fixed_msg_size(header.msg_type).is_some_and(|size| header.msg_size > size) {
+                self.warn(
+                    input,
+                    ParseWarningKind::SuspiciousSize {
+                        msg_type: header.msg_type,
+                        msg_size: header.msg_size,
+                    },
+                );
+            }
+            if self.options.metadata_only && header.msg_type == MsgType::Data {
+                self.summarize(body);
+                input = rest;
+                self.message_index += 1;
+                continue;
+            }
+            if self.is_filtered(header, body) {
+                input = rest;
+                self.message_index += 1;
+                continue;
+            }
+            let result = if header.msg_type == MsgType::Data {
+                if self.options.data_refs || self.bytes.is_none() {
+                    self.data_ref(body, header)
+                } else {
+                    self.data(body, header)
+                }
+            } else {
+                match message_body(body, header, &self.validating_options) {
+                    Err(nom::Err::Failure(ParseError {
+                        input: position,
+                        kind: UlogErrorKind::InvalidUtf8(_),
+                        ..
+                    })) if self.options.utf8_policy != Utf8Policy::Error => {
+                        self.warn(position, ParseWarningKind::InvalidUtf8);
+                        message_body(body, header, self.options)
+                    }
+                    result => result,
+                }
+            };
+            match result {
+                Ok((_, message)) => self.push(body, header, message)?,
+                Err(nom::Err::Error(ParseError {
+                    kind: UlogErrorKind::UnknownMessageType(_),
+                    ..
+                })) => {
+                    debug!(
+                        "skipping unknown message type {} of size {} at offset {}",
+                        header.msg_type, header.msg_size, offset
+                    );
+                    self.ulog
+                        .unknown_messages
+                        .push(UnknownMessage { header, offset });
+                }
+                Err(err) => self.recover(err)?,
+            }
+            input = rest;
+            self.message_index += 1;
+        }
+        Ok(input)
+    }
+}
+
+// Borrowed input is parsed into data refs first, so that only the span between the first
+// and the last data message kept is copied, instead of allocating the payloads one by one.
+fn resolve_data_refs(input: &[u8], data: &mut [Message]) {
+    let span = data
+        .iter()
+        .filter_map(|message| match message {
+            Message::DataRef(data) => Some((data.offset, data.offset + data.len)),
+            _ => None,
+        })
+        .reduce(|(start, end), (offset, data_end)| (start.min(offset), end.max(data_end)));
+    let Some((start, end)) = span else {
+        return;
+    };
+    let bytes = Bytes::copy_from_slice(&input[start..end]);
+    for message in data {
+        if let Message::DataRef(data) = message {
+            let offset = data.offset - start;
+            *message = Message::Data(MessageData {
+                header: data.header,
+                msg_id: data.msg_id,
+                data: bytes.slice(offset..offset + data.len),
+            });
+        }
+    }
+}
+
+pub fn ulog<'a>(input: &'a [u8], options: &ParseOptions) -> ParseResult<'a, Ulog> {
+    ulog_in(input, None, options, None)
+}
+
+fn ulog_in<'a, 'o>(
+    input: &'a [u8],
+    bytes: Option<Bytes>,
+    options: &'o ParseOptions,
+    progress: Option<&'o mut dyn FnMut(Progress)>,
+) -> ParseResult<'a, Ulog> {
+    let start = input;
+    let (flag_bits_input, header) = header(input)?;
+    let mut warnings = Vec::new();
+    if header.version > ULOG_VERSION {
+        let version_input = &start[7..];
+        let kind = UlogErrorKind::UnsupportedVersion(header.version);
+        if options.version_policy == VersionPolicy::Strict {
+            return Err(nom::Err::Failure(ParseError::new(version_input, kind)));
+        }
+        warnings.push(ParseWarning {
+            kind: ParseWarningKind::UnsupportedVersion(header.version),
+            offset: start.offset(version_input),
+            message_index: None,
+        });
+    }
+    let (input, message_flag_bits) = message_flag_bits(flag_bits_input, options)?;
+    if let Some(unknown) = message_flag_bits.unknown_incompat_flags() {
+        warnings.push(ParseWarning {
+            kind: ParseWarningKind::UnknownIncompatFlags(unknown),
+            offset: start.offset(flag_bits_input),
+            message_index: None,
+        });
+    }
+    if fixed_msg_size(MsgType::FlagBits)
+        .is_some_and(|size| message_flag_bits.header.msg_size > size)
+    {
+        warnings.push(ParseWarning {
+            kind: ParseWarningKind::SuspiciousSize {
+                msg_type: MsgType::FlagBits,
+                msg_size: message_flag_bits.header.msg_size,
+            },
+            offset: start.offset(flag_bits_input),
+            message_index: None,
+        });
+    }
+    #[cfg(feature = "log")]
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+
+    let mut context = Context {
+        start,
+        bytes,
+        options,
+        validating_options: options.clone().utf8_policy(Utf8Policy::Error),
+        ulog: Ulog {
             header,
             message_flag_bits,
-            messages,
+            definitions: Vec::new(),
+            data: Vec::new(),
+            unknown_messages: Vec::new(),
+            warnings,
+            truncation: None,
+            trailing_bytes: None,
+            orphan_data: Vec::new(),
+            orphan_counts: BTreeMap::new(),
+            next_log_offset: None,
+            cancelled: false,
+            data_summary: BTreeMap::new(),
         },
-    ))
+        message_index: 0,
+        subscribed: MsgIdSet::default(),
+        filtered: MsgIdSet::default(),
+        strings: StringPool::default(),
+        progress,
+        next_progress: 0,
+        formats: None,
+        summary_keys: HashMap::new(),
+    };
+    let mut data_offset = start.offset(input);
+    for boundary in context.appended_boundaries(flag_bits_input, data_offset)? {
+        context.messages(&start[data_offset..boundary], true)?;
+        debug!("parsing appended data at offset {}", boundary);
+        data_offset = boundary;
+    }
+    let rest = context.messages(&start[data_offset..], false)?;
+    if context.bytes.is_none() && !options.data_refs {
+        resolve_data_refs(start, &mut context.ulog.data);
+    }
+    context.next_progress = 0;
+    context.report_progress(start.offset(rest));
+    if !rest.is_empty() && context.ulog.next_log_offset.is_none() && !context.ulog.cancelled {
+        context.ulog.trailing_bytes = Some(TrailingBytes {
+            offset: start.offset(rest),
+            len: rest.len(),
+        });
+    }
+
+    Ok((rest, context.ulog))
+}
+
+pub fn parse_ulog(input: &[u8]) -> Result<Ulog, UlogError> {
+    parse_ulog_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_ulog_with_options(input: &[u8], options: &ParseOptions) -> Result<Ulog, UlogError> {
+    let (_, ulog) = ulog(input, options).map_err(|err| UlogError::new(err, input))?;
+    Ok(ulog)
+}
+
+pub fn parse_ulog_with_progress(
+    input: &[u8],
+    options: &ParseOptions,
+    mut progress: impl FnMut(Progress),
+) -> Result<Ulog, UlogError> {
+    let (_, ulog) = ulog_in(input, None, options, Some(&mut progress))
+        .map_err(|err| UlogError::new(err, input))?;
+    Ok(ulog)
+}
+
+pub fn scan_ulog(input: &[u8]) -> Result<Ulog, UlogError> {
+    parse_ulog_with_options(input, &ParseOptions::default().metadata_only(true))
+}
+
+pub fn parse_ulog_bytes(input: Bytes, options: &ParseOptions) -> Result<Ulog, UlogError> {
+    let (_, ulog) = ulog_in(&input, Some(input.clone()), options, None)
+        .map_err(|err| UlogError::new(err, &input))?;
+    Ok(ulog)
 }
 
-pub fn parse_ulog(input: &[u8]) -> Option<Ulog> {
-    let (_, ulog) = ulog(input).ok()?;
-    Some(ulog)
+pub fn parse_ulogs(input: &[u8], options: &ParseOptions) -> Result<Vec<Ulog>, UlogError> {
+    let mut logs = Vec::new();
+    let mut rest = input;
+    loop {
+        let (next, ulog) =
+            ulog_in(rest, None, options, None).map_err(|err| UlogError::new(err, input))?;
+        let has_next = ulog.next_log_offset.is_some();
+        logs.push(ulog);
+        if !has_next {
+            return Ok(logs);
+        }
+        rest = next;
+    }
 }
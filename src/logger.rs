@@ -0,0 +1,101 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::UlogStreamWriter;
+
+// Log levels are the ASCII digits of the syslog severities.
+fn log_level(level: Level) -> u8 {
+    match level {
+        Level::Error => b'3',
+        Level::Warn => b'4',
+        Level::Info => b'6',
+        Level::Debug | Level::Trace => b'7',
+    }
+}
+
+type Clock = Box<dyn Fn() -> u64 + Send + Sync>;
+
+// The writer is shared so the application can keep writing telemetry to the same
+// log. Write errors cannot be reported through `Log`, so those records are dropped.
+pub struct UlogLogger<W> {
+    writer: Arc<Mutex<UlogStreamWriter<W>>>,
+    level: LevelFilter,
+    tag: Option<u16>,
+    clock: Clock,
+}
+
+impl<W: Write + Send> UlogLogger<W> {
+    pub fn new(writer: Arc<Mutex<UlogStreamWriter<W>>>) -> Self {
+        let start = Instant::now();
+        UlogLogger {
+            writer,
+            level: LevelFilter::Info,
+            tag: None,
+            clock: Box::new(move || start.elapsed().as_micros() as u64),
+        }
+    }
+
+    pub fn level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    // Writes tagged logging messages instead of plain ones.
+    pub fn tag(mut self, tag: u16) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    // Timestamps default to the microseconds since the logger was created.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn writer(&self) -> &Arc<Mutex<UlogStreamWriter<W>>> {
+        &self.writer
+    }
+
+    pub fn init(self) -> Result<(), log::SetLoggerError>
+    where
+        W: 'static,
+    {
+        log::set_max_level(self.level);
+        log::set_boxed_logger(Box::new(self))
+    }
+}
+
+impl<W: Write + Send> Log for UlogLogger<W> {
+    // Records from this crate are skipped, since they may be emitted while the
+    // writer is locked.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && !metadata.target().starts_with("ulogrs")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Ok(mut writer) = self.writer.lock() else {
+            return;
+        };
+        let level = log_level(record.level());
+        let timestamp = (self.clock)();
+        let message = record.args().to_string();
+        let _ = match self.tag {
+            Some(tag) => writer.write_tagged_logging(level, tag, timestamp, &message),
+            None => writer.write_logging(level, timestamp, &message),
+        };
+    }
+
+    fn flush(&self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writer.flush();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::Path,
+};
+
+use mcap::{
+    records::{MessageHeader, Metadata},
+    Writer,
+};
+use serde_json::{json, Map, Value};
+
+use crate::{json::sample_fields, Decoder, ExportError, FieldType, Layout, Message, Ulog};
+
+fn json_type(field_type: &FieldType) -> Value {
+    match field_type {
+        FieldType::Float | FieldType::Double => json!({ "type": "number" }),
+        FieldType::Bool => json!({ "type": "boolean" }),
+        FieldType::Char | FieldType::Nested(_) => json!({ "type": "string" }),
+        _ => json!({ "type": "integer" }),
+    }
+}
+
+// A JSON Schema matching the objects written for each sample: one property per
+// field, with arrays of fixed length and char arrays as strings.
+fn json_schema(layout: &Layout) -> Value {
+    let properties: Map<String, Value> = layout
+        .fields
+        .iter()
+        .filter(|field| !field.name.starts_with("_padding"))
+        .map(|field| {
+            let schema = match (&field.field_type, field.array_len) {
+                (FieldType::Char, _) | (_, None) => json_type(&field.field_type),
+                (field_type, Some(len)) => json!({
+                    "type": "array",
+                    "items": json_type(field_type),
+                    "minItems": len,
+                    "maxItems": len,
+                }),
+            };
+            (field.name.clone(), schema)
+        })
+        .collect();
+    let required: Vec<&String> = properties.keys().collect();
+    json!({
+        "title": layout.name,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+impl Ulog {
+    // One JSON encoded channel per topic instance, named `/<topic>_<multi_id>`, with a
+    // JSON Schema per format so the log can be opened in Foxglove. Info and parameters
+    // are stored as the `ulog.info` and `ulog.parameters` metadata records. Returns the
+    // number of samples written.
+    pub fn write_mcap(&self, writer: impl Write + Seek) -> Result<usize, ExportError> {
+        let mut mcap = Writer::new(writer)?;
+        mcap.write_metadata(&Metadata {
+            name: "ulog.info".to_string(),
+            metadata: self
+                .info()
+                .into_iter()
+                .map(|(name, value)| (name, value.to_string()))
+                .collect(),
+        })?;
+        mcap.write_metadata(&Metadata {
+            name: "ulog.parameters".to_string(),
+            metadata: self
+                .parameters()
+                .into_iter()
+                .map(|(name, value)| (name, value.to_string()))
+                .collect(),
+        })?;
+
+        let mut decoder = Decoder::new(self.formats()?);
+        let mut channels: HashMap<u16, u16> = HashMap::new();
+        let mut sequences: HashMap<u16, u32> = HashMap::new();
+        let mut samples = 0;
+        for message in &self.data {
+            match message {
+                Message::AddLogged(add_logged) => {
+                    decoder.add_logged(add_logged)?;
+                    let Some(layout) = decoder.layout(add_logged.msg_id) else {
+                        continue;
+                    };
+                    let schema = json_schema(layout).to_string();
+                    let schema_id =
+                        mcap.add_schema(&layout.name, "jsonschema", schema.as_bytes())?;
+                    let metadata = BTreeMap::from([
+                        ("ulog.message_name".to_string(), layout.name.clone()),
+                        ("ulog.multi_id".to_string(), add_logged.multi_id.to_string()),
+                    ]);
+                    let topic = format!("/{}_{}", layout.name, add_logged.multi_id);
+                    let channel_id = mcap.add_channel(schema_id, &topic, "json", &metadata)?;
+                    channels.insert(add_logged.msg_id, channel_id);
+                }
+                Message::RemoveLogged(remove_logged) => {
+                    decoder.remove_logged(remove_logged.msg_id);
+                    channels.remove(&remove_logged.msg_id);
+                }
+                Message::Data(data) => {
+                    let Some(&channel_id) = channels.get(&data.msg_id) else {
+                        continue;
+                    };
+                    let sample = decoder.decode(data)?;
+                    let payload = Value::Object(sample_fields(&sample)).to_string();
+                    let sequence = sequences.entry(channel_id).or_insert(0);
+                    // ULog timestamps are microseconds, MCAP log times nanoseconds.
+                    let time = sample.timestamp().unwrap_or(0).saturating_mul(1000);
+                    let header = MessageHeader {
+                        channel_id,
+                        sequence: *sequence,
+                        log_time: time,
+                        publish_time: time,
+                    };
+                    mcap.write_to_known_channel(&header, payload.as_bytes())?;
+                    *sequence += 1;
+                    samples += 1;
+                }
+                _ => {}
+            }
+        }
+        mcap.finish()?;
+        Ok(samples)
+    }
+
+    pub fn export_mcap(&self, path: impl AsRef<Path>) -> Result<usize, ExportError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let samples = self.write_mcap(&mut writer)?;
+        writer.flush()?;
+        Ok(samples)
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt,
+    sync::Arc,
+};
+
+use crate::{
+    FormatDefinition, FormatError, Message, MessageAddLogged, MessageParameter,
+    MessageRemoveLogged, MsgType, Ulog,
+};
+
+#[derive(Debug)]
+pub enum MergeError {
+    Empty,
+    Format(FormatError),
+    FormatConflict(String),
+    TooManySubscriptions,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::Empty => write!(f, "no logs to merge"),
+            MergeError::Format(err) => write!(f, "{}", err),
+            MergeError::FormatConflict(name) => {
+                write!(f, "format {:?} differs between the merged logs", name)
+            }
+            MergeError::TooManySubscriptions => write!(f, "all msg_ids are in use"),
+        }
+    }
+}
+
+impl Error for MergeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MergeError::Format(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FormatError> for MergeError {
+    fn from(err: FormatError) -> Self {
+        MergeError::Format(err)
+    }
+}
+
+// Keyed by name rather than the full key, which also spells out the value's type and length.
+fn definition_key(message: &Message) -> Option<(MsgType, String, u8)> {
+    let name = |key: &str| {
+        key.split_once(' ')
+            .map_or(key, |(_, name)| name)
+            .to_string()
+    };
+    match message {
+        Message::Info(info) => Some((MsgType::Info, name(&info.key), 0)),
+        Message::InfoMultiple(info) => Some((MsgType::InfoMultiple, name(&info.key), 0)),
+        Message::ParameterDefault(parameter) => Some((
+            MsgType::ParameterDefault,
+            name(&parameter.key),
+            parameter.default_types.bits(),
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Merger {
+    formats: HashMap<String, FormatDefinition>,
+    keys: HashSet<(MsgType, String, u8)>,
+    parameters: BTreeMap<Arc<str>, Vec<u8>>,
+    msg_ids: HashMap<(Arc<str>, u8), u16>,
+    active: HashSet<u16>,
+    definitions: Vec<Message>,
+    data: Vec<Message>,
+}
+
+impl Merger {
+    fn add_format(
+        &mut self,
+        message: &Message,
+        definition: FormatDefinition,
+    ) -> Result<(), MergeError> {
+        match self.formats.get(&definition.name) {
+            Some(existing) if *existing == definition => Ok(()),
+            Some(_) => Err(MergeError::FormatConflict(definition.name)),
+            None => {
+                self.formats.insert(definition.name.clone(), definition);
+                self.definitions.push(message.clone());
+                Ok(())
+            }
+        }
+    }
+
+    // Parameters that differ from the merged state become changes in the data section.
+    fn carry_parameter(&mut self, parameter: &MessageParameter, first: bool) {
+        if self.parameters.get(&parameter.key) == Some(&parameter.value) {
+            return;
+        }
+        self.parameters
+            .insert(parameter.key.clone(), parameter.value.clone());
+        let message = Message::Parameter(parameter.clone());
+        if first {
+            self.definitions.push(message);
+        } else {
+            self.data.push(message);
+        }
+    }
+
+    fn definitions(&mut self, ulog: &Ulog, first: bool) -> Result<(), MergeError> {
+        let known = self.keys.clone();
+        for message in &ulog.definitions {
+            match message {
+                Message::Format(format) => {
+                    let definition = format.definition().map_err(|error| FormatError::Invalid {
+                        format: format.format.clone(),
+                        error,
+                    })?;
+                    self.add_format(message, definition)?;
+                }
+                Message::Parameter(parameter) => self.carry_parameter(parameter, first),
+                message => match definition_key(message) {
+                    Some(key) if known.contains(&key) => {}
+                    Some(key) => {
+                        self.keys.insert(key);
+                        self.definitions.push(message.clone());
+                    }
+                    None => self.definitions.push(message.clone()),
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn data(&mut self, ulog: &Ulog) -> Result<(), MergeError> {
+        let mut remap: HashMap<u16, u16> = HashMap::new();
+        for message in &ulog.data {
+            match message {
+                Message::AddLogged(add_logged) => {
+                    let key = (add_logged.message_name.clone(), add_logged.multi_id);
+                    let next = self.msg_ids.len();
+                    let msg_id = match self.msg_ids.get(&key) {
+                        Some(&msg_id) => msg_id,
+                        None => {
+                            let msg_id = u16::try_from(next)
+                                .map_err(|_| MergeError::TooManySubscriptions)?;
+                            self.msg_ids.insert(key, msg_id);
+                            msg_id
+                        }
+                    };
+                    remap.insert(add_logged.msg_id, msg_id);
+                    if self.active.insert(msg_id) {
+                        self.data.push(Message::AddLogged(MessageAddLogged {
+                            msg_id,
+                            ..add_logged.clone()
+                        }));
+                    }
+                }
+                Message::RemoveLogged(remove_logged) => {
+                    if let Some(msg_id) = remap.remove(&remove_logged.msg_id) {
+                        self.active.remove(&msg_id);
+                        self.data
+                            .push(Message::RemoveLogged(MessageRemoveLogged::new(msg_id)));
+                    }
+                }
+                Message::Data(data) => {
+                    if let Some(&msg_id) = remap.get(&data.msg_id) {
+                        let mut data = data.clone();
+                        data.msg_id = msg_id;
+                        self.data.push(Message::Data(data));
+                    }
+                }
+                Message::DataRef(data) => {
+                    if let Some(&msg_id) = remap.get(&data.msg_id) {
+                        let mut data = *data;
+                        data.msg_id = msg_id;
+                        self.data.push(Message::DataRef(data));
+                    }
+                }
+                Message::Parameter(parameter) => {
+                    self.parameters
+                        .insert(parameter.key.clone(), parameter.value.clone());
+                    self.data.push(message.clone());
+                }
+                message => self.data.push(message.clone()),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Logs are ordered by their start time, topics keep one msg_id across all of them,
+// and formats with the same name must match.
+pub fn merge<'a>(logs: impl IntoIterator<Item = &'a Ulog>) -> Result<Ulog, MergeError> {
+    let mut ordered: Vec<&Ulog> = logs.into_iter().collect();
+    ordered.sort_by_key(|ulog| ulog.header.timestamp);
+    let (&first, _) = ordered.split_first().ok_or(MergeError::Empty)?;
+
+    let mut merger = Merger::default();
+    for (index, ulog) in ordered.iter().enumerate() {
+        merger.definitions(ulog, index == 0)?;
+        merger.data(ulog)?;
+    }
+    Ok(first.rewritten(merger.definitions, merger.data))
+}
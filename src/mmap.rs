@@ -0,0 +1,101 @@
+use std::{fs::File, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{
+    find_next_sync, parse_ulog_with_options, Dataset, DecodeError, DecodedData, Decoder, Message,
+    MessageDataRef, ParseOptions, Ulog, UlogError, UlogErrorKind,
+};
+
+#[derive(Debug)]
+pub struct MappedUlog {
+    mmap: Mmap,
+    // The data are refs into the map, so query them with the methods below: the `Ulog`
+    // queries return `DecodeError::UnresolvedDataRef`.
+    pub ulog: Ulog,
+}
+
+impl MappedUlog {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, UlogError> {
+        Self::open_with_options(path, &ParseOptions::default())
+    }
+
+    pub fn open_with_options(
+        path: impl AsRef<Path>,
+        options: &ParseOptions,
+    ) -> Result<Self, UlogError> {
+        let io_error = |err| UlogError {
+            kind: UlogErrorKind::Io(err),
+            offset: 0,
+            message_index: None,
+        };
+        let file = File::open(path).map_err(io_error)?;
+        // SAFETY: the map is only read, but the log must not be truncated while it is mapped.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+        let ulog = parse_ulog_with_options(&mmap, &options.clone().data_refs(true))?;
+        Ok(MappedUlog { mmap, ulog })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    pub fn find_next_sync(&self, offset: usize) -> Option<usize> {
+        find_next_sync(&self.mmap, offset)
+    }
+
+    pub fn payload(&self, data: &MessageDataRef) -> &[u8] {
+        &self.mmap[data.offset..data.offset + data.len]
+    }
+
+    pub fn payloads(&self, msg_id: u16) -> impl Iterator<Item = &[u8]> {
+        self.ulog
+            .data
+            .iter()
+            .filter_map(move |message| match message {
+                Message::DataRef(data) if data.msg_id == msg_id => Some(self.payload(data)),
+                _ => None,
+            })
+    }
+
+    fn for_each_data(
+        &self,
+        message_name: Option<(&str, u8)>,
+        mut f: impl FnMut(DecodedData),
+    ) -> Result<(), DecodeError> {
+        let mut decoder = Decoder::new(self.ulog.formats()?);
+        for message in &self.ulog.data {
+            match message {
+                Message::AddLogged(add_logged)
+                    if message_name.is_none_or(|(name, multi_id)| {
+                        *add_logged.message_name == *name && add_logged.multi_id == multi_id
+                    }) =>
+                {
+                    decoder.add_logged(add_logged)?
+                }
+                Message::AddLogged(add_logged) => decoder.remove_logged(add_logged.msg_id),
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                Message::DataRef(data) if decoder.is_subscribed(data.msg_id) => {
+                    f(decoder.decode_payload(data.msg_id, self.payload(data))?)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn decoded_data(&self) -> Result<Vec<DecodedData>, DecodeError> {
+        let mut decoded = Vec::new();
+        self.for_each_data(None, |data| decoded.push(data))?;
+        Ok(decoded)
+    }
+
+    pub fn dataset(&self, message_name: &str, multi_id: u8) -> Result<Dataset, DecodeError> {
+        let layout = self.ulog.formats()?.layout(message_name)?;
+        let mut dataset = Dataset::new(&layout, multi_id);
+        self.for_each_data(Some((message_name, multi_id)), |data| {
+            dataset.push(data.values)
+        })?;
+        Ok(dataset)
+    }
+}
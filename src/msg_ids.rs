@@ -0,0 +1,46 @@
+// A bit per msg_id: membership tests run once per data message, so they must not hash.
+#[derive(Debug, Clone)]
+pub(crate) struct MsgIdSet {
+    bits: Box<[u64]>,
+    len: usize,
+}
+
+impl Default for MsgIdSet {
+    fn default() -> Self {
+        MsgIdSet {
+            bits: vec![0; (usize::from(u16::MAX) + 1) / 64].into_boxed_slice(),
+            len: 0,
+        }
+    }
+}
+
+impl MsgIdSet {
+    fn bit(msg_id: u16) -> (usize, u64) {
+        (usize::from(msg_id / 64), 1 << (msg_id % 64))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn contains(&self, msg_id: u16) -> bool {
+        let (word, mask) = Self::bit(msg_id);
+        self.bits[word] & mask != 0
+    }
+
+    pub(crate) fn insert(&mut self, msg_id: u16) {
+        if !self.contains(msg_id) {
+            let (word, mask) = Self::bit(msg_id);
+            self.bits[word] |= mask;
+            self.len += 1;
+        }
+    }
+
+    pub(crate) fn remove(&mut self, msg_id: u16) {
+        if self.contains(msg_id) {
+            let (word, mask) = Self::bit(msg_id);
+            self.bits[word] &= !mask;
+            self.len -= 1;
+        }
+    }
+}
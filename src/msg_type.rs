@@ -0,0 +1,67 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MsgType {
+    FlagBits,
+    Format,
+    Info,
+    InfoMultiple,
+    Parameter,
+    ParameterDefault,
+    AddLogged,
+    RemoveLogged,
+    Data,
+    Logging,
+    LoggingTagged,
+    Sync,
+    Dropout,
+    Unknown(u8),
+}
+
+impl From<u8> for MsgType {
+    fn from(msg_type: u8) -> Self {
+        match msg_type {
+            b'B' => MsgType::FlagBits,
+            b'F' => MsgType::Format,
+            b'I' => MsgType::Info,
+            b'M' => MsgType::InfoMultiple,
+            b'P' => MsgType::Parameter,
+            b'Q' => MsgType::ParameterDefault,
+            b'A' => MsgType::AddLogged,
+            b'R' => MsgType::RemoveLogged,
+            b'D' => MsgType::Data,
+            b'L' => MsgType::Logging,
+            b'C' => MsgType::LoggingTagged,
+            b'S' => MsgType::Sync,
+            b'O' => MsgType::Dropout,
+            msg_type => MsgType::Unknown(msg_type),
+        }
+    }
+}
+
+impl From<MsgType> for u8 {
+    fn from(msg_type: MsgType) -> Self {
+        match msg_type {
+            MsgType::FlagBits => b'B',
+            MsgType::Format => b'F',
+            MsgType::Info => b'I',
+            MsgType::InfoMultiple => b'M',
+            MsgType::Parameter => b'P',
+            MsgType::ParameterDefault => b'Q',
+            MsgType::AddLogged => b'A',
+            MsgType::RemoveLogged => b'R',
+            MsgType::Data => b'D',
+            MsgType::Logging => b'L',
+            MsgType::LoggingTagged => b'C',
+            MsgType::Sync => b'S',
+            MsgType::Dropout => b'O',
+            MsgType::Unknown(msg_type) => msg_type,
+        }
+    }
+}
+
+impl fmt::Display for MsgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", u8::from(*self) as char)
+    }
+}
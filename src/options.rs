@@ -0,0 +1,190 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Utf8Policy {
+    #[default]
+    Lossy,
+    Raw,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    Strict,
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionPolicy {
+    Strict,
+    #[default]
+    AttemptNewer,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    #[default]
+    Keep,
+    Drop,
+    Error,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TopicFilter {
+    #[default]
+    All,
+    Only(HashSet<String>),
+    Exclude(HashSet<String>),
+}
+
+impl TopicFilter {
+    pub fn allows(&self, message_name: &str) -> bool {
+        match self {
+            TopicFilter::All => true,
+            TopicFilter::Only(topics) => topics.contains(message_name),
+            TopicFilter::Exclude(topics) => !topics.contains(message_name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    pub utf8_policy: Utf8Policy,
+    pub allow_unknown_incompat_flags: bool,
+    pub version_policy: VersionPolicy,
+    pub max_messages: Option<usize>,
+    pub max_message_size: Option<u16>,
+    pub max_bytes: Option<usize>,
+    pub orphan_policy: OrphanPolicy,
+    pub topic_filter: TopicFilter,
+    pub data_refs: bool,
+    pub cancellation: Option<CancellationToken>,
+    pub metadata_only: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn strict() -> Self {
+        Self::new().mode(ParseMode::Strict)
+    }
+
+    pub fn lenient() -> Self {
+        Self::new().mode(ParseMode::Lenient)
+    }
+
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn utf8_policy(mut self, utf8_policy: Utf8Policy) -> Self {
+        self.utf8_policy = utf8_policy;
+        self
+    }
+
+    pub fn allow_unknown_incompat_flags(mut self, allow: bool) -> Self {
+        self.allow_unknown_incompat_flags = allow;
+        self
+    }
+
+    pub fn version_policy(mut self, version_policy: VersionPolicy) -> Self {
+        self.version_policy = version_policy;
+        self
+    }
+
+    pub fn max_messages(mut self, max_messages: usize) -> Self {
+        self.max_messages = Some(max_messages);
+        self
+    }
+
+    pub fn max_message_size(mut self, max_message_size: u16) -> Self {
+        self.max_message_size = Some(max_message_size);
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn orphan_policy(mut self, orphan_policy: OrphanPolicy) -> Self {
+        self.orphan_policy = orphan_policy;
+        self
+    }
+
+    pub fn topic_filter(mut self, topic_filter: TopicFilter) -> Self {
+        self.topic_filter = topic_filter;
+        self
+    }
+
+    pub fn data_refs(mut self, data_refs: bool) -> Self {
+        self.data_refs = data_refs;
+        self
+    }
+
+    pub fn metadata_only(mut self, metadata_only: bool) -> Self {
+        self.metadata_only = metadata_only;
+        self
+    }
+
+    pub fn cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    pub fn only_topics<I, S>(self, topics: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.topic_filter(TopicFilter::Only(
+            topics.into_iter().map(Into::into).collect(),
+        ))
+    }
+
+    pub fn exclude_topics<I, S>(self, topics: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.topic_filter(TopicFilter::Exclude(
+            topics.into_iter().map(Into::into).collect(),
+        ))
+    }
+}
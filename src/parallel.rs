@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::{
+    decode::{check_payload, decode_layout},
+    Dataset, DecodeError, DecodedData, Decoder, Layout, Message, MessageData, Ulog,
+};
+
+struct Job<'a> {
+    data: &'a MessageData,
+    multi_id: u8,
+    layout: Arc<Layout>,
+}
+
+impl Job<'_> {
+    fn decode(&self) -> Result<DecodedData, DecodeError> {
+        decode_layout(
+            self.data.msg_id,
+            self.multi_id,
+            &self.layout,
+            &self.data.data,
+        )
+    }
+}
+
+const CHUNK_SIZE: usize = 4096;
+
+impl Ulog {
+    fn data_jobs(&self, message_name: Option<(&str, u8)>) -> Result<Vec<Job<'_>>, DecodeError> {
+        let mut decoder = Decoder::new(self.formats()?);
+        let mut jobs = Vec::new();
+        for message in &self.data {
+            match message {
+                Message::AddLogged(add_logged)
+                    if message_name.is_none_or(|(name, multi_id)| {
+                        *add_logged.message_name == *name && add_logged.multi_id == multi_id
+                    }) =>
+                {
+                    decoder.add_logged(add_logged)?
+                }
+                Message::AddLogged(add_logged) => decoder.remove_logged(add_logged.msg_id),
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                Message::Data(data) => match decoder.subscription(data.msg_id) {
+                    Some((multi_id, layout)) => jobs.push(Job {
+                        data,
+                        multi_id,
+                        layout: layout.clone(),
+                    }),
+                    None if message_name.is_none() => {
+                        return Err(DecodeError::UnknownMsgId(data.msg_id))
+                    }
+                    None => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(jobs)
+    }
+
+    pub fn par_decoded_data(&self) -> Result<Vec<DecodedData>, DecodeError> {
+        let chunks = self
+            .data_jobs(None)?
+            .par_chunks(CHUNK_SIZE)
+            .map(|chunk| chunk.iter().map(Job::decode).collect())
+            .collect::<Vec<Result<Vec<DecodedData>, DecodeError>>>();
+        let mut decoded = Vec::new();
+        for chunk in chunks {
+            decoded.extend(chunk?);
+        }
+        Ok(decoded)
+    }
+
+    pub fn par_dataset(&self, message_name: &str, multi_id: u8) -> Result<Dataset, DecodeError> {
+        let layout = self.formats()?.layout(message_name)?;
+        let jobs = self.data_jobs(Some((message_name, multi_id)))?;
+        let chunks = jobs
+            .par_chunks(CHUNK_SIZE)
+            .map(|chunk| {
+                let mut dataset = Dataset::new(&layout, multi_id);
+                for job in chunk {
+                    check_payload(job.data.msg_id, &layout, &job.data.data)?;
+                    dataset.push_payload(&layout, &job.data.data);
+                }
+                Ok(dataset)
+            })
+            .collect::<Vec<Result<Dataset, DecodeError>>>();
+        let mut dataset = Dataset::new(&layout, multi_id);
+        for chunk in chunks {
+            dataset.extend(&chunk?);
+        }
+        Ok(dataset)
+    }
+}
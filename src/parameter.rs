@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, fmt};
+
+use bitflags::bitflags;
+
+use crate::{Field, FieldType, MessageParameter, MessageParameterDefault};
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct DefaultTypes: u8 {
+        const SYSTEM = 1 << 0;
+        const CURRENT_SETUP = 1 << 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterValue {
+    Float(f32),
+    Int32(i32),
+}
+
+impl ParameterValue {
+    pub fn parse(key: &str, value: &[u8]) -> Option<Self> {
+        let field = Field::parse_key(key)?;
+        let value = value.try_into().ok()?;
+        match field.field_type {
+            _ if field.array_len.is_some() => None,
+            FieldType::Float => Some(ParameterValue::Float(f32::from_le_bytes(value))),
+            FieldType::Int32 => Some(ParameterValue::Int32(i32::from_le_bytes(value))),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            ParameterValue::Float(v) => v.into(),
+            ParameterValue::Int32(v) => v.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterValue::Float(v) => write!(f, "{}", v),
+            ParameterValue::Int32(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterChange {
+    pub index: usize,
+    pub timestamp: Option<u64>,
+    pub value: ParameterValue,
+    pub initial: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefaultParameters {
+    pub system: BTreeMap<String, ParameterValue>,
+    pub current_setup: BTreeMap<String, ParameterValue>,
+}
+
+impl DefaultParameters {
+    pub fn insert(&mut self, default: &MessageParameterDefault) {
+        let (Some(name), Some(value)) = (default.name(), default.typed_value()) else {
+            return;
+        };
+        if default.default_types.contains(DefaultTypes::SYSTEM) {
+            self.system.insert(name.to_string(), value);
+        }
+        if default.default_types.contains(DefaultTypes::CURRENT_SETUP) {
+            self.current_setup.insert(name.to_string(), value);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<ParameterValue> {
+        self.current_setup
+            .get(name)
+            .or_else(|| self.system.get(name))
+            .copied()
+    }
+
+    pub fn differing(
+        &self,
+        parameters: &BTreeMap<String, ParameterValue>,
+    ) -> BTreeMap<String, (ParameterValue, ParameterValue)> {
+        parameters
+            .iter()
+            .filter_map(|(name, &value)| {
+                let default = self.get(name)?;
+                (default != value).then(|| (name.clone(), (value, default)))
+            })
+            .collect()
+    }
+}
+
+impl MessageParameter {
+    pub fn name(&self) -> Option<&str> {
+        self.key.split_once(' ').map(|(_, name)| name)
+    }
+
+    pub fn typed_value(&self) -> Option<ParameterValue> {
+        ParameterValue::parse(&self.key, &self.value)
+    }
+}
+
+impl MessageParameterDefault {
+    pub fn name(&self) -> Option<&str> {
+        self.key.split_once(' ').map(|(_, name)| name)
+    }
+
+    pub fn typed_value(&self) -> Option<ParameterValue> {
+        ParameterValue::parse(&self.key, &self.value)
+    }
+}
@@ -0,0 +1,47 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+
+use crate::{Dataset, ExportError, Ulog};
+
+pub fn write_parquet(dataset: Dataset, writer: impl Write + Send) -> Result<(), ExportError> {
+    let batch = dataset.into_record_batch()?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+impl Ulog {
+    // Writes `<base_name>_<topic>_<multi_id>.parquet` for every topic instance, with
+    // the same naming as `export_csv`.
+    pub fn export_parquet(
+        &self,
+        output_dir: impl AsRef<Path>,
+        base_name: &str,
+    ) -> Result<Vec<PathBuf>, ExportError> {
+        let instances = self.topic_instances();
+        let mut paths = Vec::with_capacity(instances.len());
+        for (message_name, multi_id) in instances {
+            let dataset = self.dataset(&message_name, multi_id)?;
+            let path = output_dir.as_ref().join(format!(
+                "{}_{}_{}.parquet",
+                base_name, message_name, multi_id
+            ));
+            write_parquet(dataset, File::create(&path)?)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
@@ -0,0 +1,648 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use nom::Offset;
+
+use crate::{
+    find_sync, fixed_msg_size, header, intern::StringPool, is_corrupt_msg_type, message_body,
+    message_flag_bits, message_header, Decoder, Formats, Header, Limit, Message, MessageFlagBits,
+    MessageHeader, MsgType, OrphanPolicy, ParseError, ParseMode, ParseOptions, ParseWarning, ParseWarningKind,
+    Subscriptions, TopicFilter, UlogError, UlogErrorKind, UnknownMessage, Utf8Policy,
+    VersionPolicy, MAGIC, SYNC_MAGIC, ULOG_VERSION,
+};
+
+const HEADER_SIZE: usize = 16;
+const MESSAGE_HEADER_SIZE: usize = 3;
+
+#[derive(Debug, Default)]
+pub struct UlogParser {
+    options: ParseOptions,
+    buffer: Vec<u8>,
+    position: usize,
+    consumed: usize,
+    message_index: usize,
+    failed: bool,
+    eof: bool,
+    // Where sync was lost and the message type that lost it, while looking for a marker.
+    lost_sync: Option<(usize, MsgType)>,
+    // Offsets of the appended data not reached yet.
+    appended: VecDeque<usize>,
+    invalid_appended: Option<u64>,
+    strings: StringPool,
+    pub header: Option<Header>,
+    pub message_flag_bits: Option<MessageFlagBits>,
+    pub decoder: Decoder,
+    pub subscriptions: Subscriptions,
+    pub unknown_messages: Vec<UnknownMessage>,
+    pub warnings: Vec<ParseWarning>,
+    pub truncation: Option<UlogError>,
+    pub orphan_counts: BTreeMap<u16, usize>,
+    pub next_log_offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputEnd {
+    More,
+    Boundary,
+    Eof,
+}
+
+impl UlogParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        UlogParser {
+            options,
+            ..Self::default()
+        }
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    pub fn formats(&self) -> &Formats {
+        &self.decoder.formats
+    }
+
+    pub fn offset(&self) -> usize {
+        self.consumed + self.position
+    }
+
+    pub fn message_count(&self) -> usize {
+        self.message_index
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.position = 0;
+        self.consumed = 0;
+        self.message_index = 0;
+        self.failed = false;
+        self.eof = false;
+        self.lost_sync = None;
+        self.appended.clear();
+        self.invalid_appended = None;
+        self.header = None;
+        self.message_flag_bits = None;
+        self.decoder.clear();
+        self.subscriptions.clear();
+        self.unknown_messages.clear();
+        self.warnings.clear();
+        self.truncation = None;
+        self.orphan_counts.clear();
+        self.next_log_offset = None;
+    }
+
+    pub(crate) fn resume_at(&mut self, offset: usize, message_index: usize) {
+        self.consumed = offset;
+        self.message_index = message_index;
+    }
+
+    pub(crate) fn resume_appended(&mut self) {
+        let Some(flag_bits) = &self.message_flag_bits else {
+            return;
+        };
+        if flag_bits.has_data_appended() {
+            self.appended = flag_bits
+                .appended_offsets()
+                .filter_map(|offset| usize::try_from(offset).ok())
+                .collect();
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Feed<'_> {
+        self.buffer.drain(..self.position);
+        self.consumed += self.position;
+        self.position = 0;
+        self.buffer.extend_from_slice(chunk);
+        Feed { parser: self }
+    }
+
+    // Marks the end of the input. In lenient mode the bytes left in the buffer are
+    // handled by `next_message`, which resyncs past them like `parse_ulog` or records
+    // the truncation, so keep calling it until it returns None.
+    pub fn finish(&mut self) -> Result<(), UlogError> {
+        self.eof = true;
+        if self.failed || self.options.is_cancelled() {
+            return Ok(());
+        }
+        if let Err(err) = self.check_appended() {
+            self.failed = true;
+            return Err(err);
+        }
+        if self.failed
+            || self.options.is_cancelled()
+            || self.buffered() == 0 && self.header.is_some()
+        {
+            return Ok(());
+        }
+        if self.header.is_some() && self.options.mode == ParseMode::Lenient {
+            return Ok(());
+        }
+        self.failed = true;
+        let offset = match self.header {
+            Some(_) => self.offset(),
+            None => self.consumed + self.buffer.len(),
+        };
+        Err(UlogError {
+            kind: UlogErrorKind::Truncated,
+            offset,
+            message_index: Some(self.message_index),
+        })
+    }
+
+    fn error_at(&self, err: nom::Err<ParseError<'_>>, start: usize) -> UlogError {
+        let mut err = UlogError::new(err, &self.buffer[start..]);
+        err.offset += self.consumed + start;
+        err.message_index = Some(self.message_index);
+        err
+    }
+
+    fn fatal(&mut self, err: UlogError) -> Option<Result<Message, UlogError>> {
+        self.failed = true;
+        Some(Err(err))
+    }
+
+    fn warn(&mut self, kind: ParseWarningKind, offset: usize) {
+        self.warnings.push(ParseWarning {
+            kind,
+            offset,
+            message_index: Some(self.message_index),
+        });
+    }
+
+    fn header_warning(&mut self, kind: ParseWarningKind, offset: usize) {
+        self.warnings.push(ParseWarning {
+            kind,
+            offset,
+            message_index: None,
+        });
+    }
+
+    fn limit(&self, end: usize, header: MessageHeader) -> Option<Limit> {
+        let options = &self.options;
+        options
+            .max_messages
+            .filter(|&max| self.message_index >= max)
+            .map(Limit::Messages)
+            .or_else(|| {
+                options
+                    .max_message_size
+                    .filter(|&max| header.msg_size > max)
+                    .map(Limit::MessageSize)
+            })
+            .or_else(|| {
+                options
+                    .max_bytes
+                    .filter(|&max| self.consumed + end > max)
+                    .map(Limit::Bytes)
+            })
+    }
+
+    // Parses the flag bits and validates the appended data offsets like `parse_ulog`.
+    fn flag_bits(&mut self, start: usize, end: usize) -> Result<(), UlogError> {
+        let flag_bits = match message_flag_bits(&self.buffer[start..end], &self.options) {
+            Ok((_, flag_bits)) => flag_bits,
+            Err(err) => return Err(self.error_at(err, start)),
+        };
+        let offset = self.consumed + start;
+        if let Some(unknown) = flag_bits.unknown_incompat_flags() {
+            self.header_warning(ParseWarningKind::UnknownIncompatFlags(unknown), offset);
+        }
+        if fixed_msg_size(MsgType::FlagBits).is_some_and(|size| flag_bits.header.msg_size > size) {
+            let kind = ParseWarningKind::SuspiciousSize {
+                msg_type: MsgType::FlagBits,
+                msg_size: flag_bits.header.msg_size,
+            };
+            self.header_warning(kind, offset);
+        }
+        if flag_bits.has_data_appended() {
+            let mut previous = self.consumed + end;
+            for appended_offset in flag_bits.appended_offsets() {
+                match usize::try_from(appended_offset) {
+                    Ok(boundary) if boundary >= previous => {
+                        self.appended.push_back(boundary);
+                        previous = boundary;
+                    }
+                    _ => {
+                        self.invalid_appended = Some(appended_offset);
+                        break;
+                    }
+                }
+            }
+        }
+        self.message_flag_bits = Some(flag_bits);
+        self.check_appended()
+    }
+
+    // Reported before the first message, where `parse_ulog` validates the offsets.
+    fn invalid_appended_offset(
+        &mut self,
+        appended_offset: u64,
+        offset: usize,
+    ) -> Result<(), UlogError> {
+        let kind = UlogErrorKind::InvalidAppendedOffset(appended_offset);
+        if self.options.mode == ParseMode::Strict {
+            return Err(UlogError {
+                kind,
+                offset,
+                message_index: Some(0),
+            });
+        }
+        let warning = ParseWarning {
+            kind: ParseWarningKind::Recovered(kind),
+            offset,
+            message_index: Some(0),
+        };
+        let at = self
+            .warnings
+            .iter()
+            .take_while(|warning| warning.message_index.is_none())
+            .count();
+        self.warnings.insert(at, warning);
+        Ok(())
+    }
+
+    // Reports the first invalid appended data offset once the offsets before it have
+    // been reached, since whether they are past the end is only known then.
+    fn check_appended(&mut self) -> Result<(), UlogError> {
+        let len = self.consumed + self.buffer.len();
+        let past_end = self
+            .appended
+            .iter()
+            .position(|&boundary| boundary > len)
+            .filter(|_| self.eof);
+        let invalid = match past_end {
+            Some(index) => {
+                let boundary = self.appended[index];
+                self.appended.truncate(index);
+                self.invalid_appended = None;
+                Some(boundary as u64)
+            }
+            None if self.appended.is_empty() => self.invalid_appended.take(),
+            None => None,
+        };
+        match invalid {
+            Some(appended_offset) => self.invalid_appended_offset(appended_offset, HEADER_SIZE),
+            None => Ok(()),
+        }
+    }
+
+    // Returns where the input parsed so far ends: at the next appended data offset, which
+    // `parse_ulog` parses as a separate region, or at the end of the buffer.
+    fn input_end(&mut self) -> Result<(usize, InputEnd), UlogError> {
+        while self
+            .appended
+            .front()
+            .is_some_and(|&boundary| boundary <= self.offset())
+        {
+            self.appended.pop_front();
+            self.check_appended()?;
+        }
+        Ok(match self.appended.front() {
+            Some(&boundary) if boundary - self.consumed <= self.buffer.len() => {
+                (boundary - self.consumed, InputEnd::Boundary)
+            }
+            _ if self.eof => (self.buffer.len(), InputEnd::Eof),
+            _ => (self.buffer.len(), InputEnd::More),
+        })
+    }
+
+    // Looks for the next sync marker while sync is lost. Returns false if more input
+    // is needed.
+    fn resync(&mut self, end: usize, input_end: InputEnd) -> bool {
+        let Some((lost_at, msg_type)) = self.lost_sync else {
+            return true;
+        };
+        match find_sync(&self.buffer[self.position..end]) {
+            Some(skip) => {
+                self.position += skip;
+                let skipped_bytes = self.offset() - lost_at;
+                self.warn(ParseWarningKind::SyncLost { skipped_bytes }, lost_at);
+                self.lost_sync = None;
+                true
+            }
+            None if input_end == InputEnd::More => {
+                // Keep enough bytes to find a marker split across chunks.
+                let keep = SYNC_MAGIC.len() + MESSAGE_HEADER_SIZE - 1;
+                self.position = self.position.max(end.saturating_sub(keep));
+                false
+            }
+            None => {
+                // No marker left: skip the rest of the region like `parse_ulog`.
+                let kind = UlogErrorKind::UnknownMessageType(msg_type.into());
+                self.warn(ParseWarningKind::Recovered(kind), lost_at + 2);
+                self.lost_sync = None;
+                self.position = end;
+                self.failed = input_end == InputEnd::Eof;
+                input_end == InputEnd::Boundary
+            }
+        }
+    }
+
+    // Handles a frame that does not fit in the input. At the end of the input it resyncs
+    // past it or records the truncation, like `parse_ulog`. Returns true to go on.
+    fn incomplete(
+        &mut self,
+        start: usize,
+        end: usize,
+        input_end: InputEnd,
+    ) -> Result<bool, UlogError> {
+        match input_end {
+            InputEnd::More => return Ok(false),
+            // Appended data may start in the middle of a message cut off by the crash.
+            InputEnd::Boundary => {
+                self.position = end;
+                return Ok(true);
+            }
+            InputEnd::Eof if start == end => return Ok(false),
+            InputEnd::Eof => {}
+        }
+        let err = UlogError {
+            kind: UlogErrorKind::Truncated,
+            offset: self.consumed + start,
+            message_index: Some(self.message_index),
+        };
+        if self.options.mode == ParseMode::Strict {
+            return Err(err);
+        }
+        match find_sync(&self.buffer[start + 1..end]) {
+            Some(skip) => {
+                let skipped_bytes = skip + 1;
+                self.warn(
+                    ParseWarningKind::SyncLost { skipped_bytes },
+                    self.consumed + start,
+                );
+                self.position = start + skipped_bytes;
+                Ok(true)
+            }
+            None => {
+                self.truncation = Some(err);
+                self.failed = true;
+                Ok(false)
+            }
+        }
+    }
+
+    fn parse_header(&mut self) -> Result<bool, UlogError> {
+        let input = &self.buffer[self.position..];
+        if input.len() < HEADER_SIZE {
+            let len = input.len().min(MAGIC.len());
+            if input[..len] != MAGIC[..len] {
+                return Err(UlogError {
+                    kind: UlogErrorKind::BadMagic,
+                    offset: self.consumed + self.position,
+                    message_index: None,
+                });
+            }
+            return Ok(false);
+        }
+        let file_header = match header(input) {
+            Ok((_, file_header)) => file_header,
+            Err(err) => return Err(self.error_at(err, self.position)),
+        };
+        if file_header.version > ULOG_VERSION {
+            let kind = UlogErrorKind::UnsupportedVersion(file_header.version);
+            let offset = self.consumed + self.position + MAGIC.len();
+            if self.options.version_policy == VersionPolicy::Strict {
+                return Err(UlogError {
+                    kind,
+                    offset,
+                    message_index: None,
+                });
+            }
+            self.header_warning(
+                ParseWarningKind::UnsupportedVersion(file_header.version),
+                offset,
+            );
+        }
+        self.header = Some(file_header);
+        self.position += HEADER_SIZE;
+        Ok(true)
+    }
+
+    pub fn next_message(&mut self) -> Option<Result<Message, UlogError>> {
+        if self.failed || self.options.is_cancelled() {
+            return None;
+        }
+        if self.header.is_none() {
+            match self.parse_header() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(err) => return self.fatal(err),
+            }
+        }
+        loop {
+            let (end, input_end) = match self.input_end() {
+                Ok(end) => end,
+                Err(err) => return self.fatal(err),
+            };
+            if !self.resync(end, input_end) {
+                return None;
+            }
+            let start = self.position;
+            let input = &self.buffer[start..end];
+            let len = input.len().min(MAGIC.len());
+            if len == MAGIC.len() && input[..len] == MAGIC {
+                self.next_log_offset = Some(self.consumed + start);
+                self.failed = true;
+                return None;
+            }
+            if input[..len] == MAGIC[..len] && input_end == InputEnd::More {
+                return None;
+            }
+            let frame = message_header(input).ok().map(|(_, header)| {
+                (
+                    header,
+                    start + MESSAGE_HEADER_SIZE + usize::from(header.msg_size),
+                )
+            });
+            // The flag bits must come first, so they fail as soon as they cannot be parsed.
+            if self.message_flag_bits.is_none()
+                && (input_end != InputEnd::More
+                    || frame.is_some_and(|(header, frame_end)| {
+                        header.msg_type != MsgType::FlagBits || frame_end <= end
+                    }))
+            {
+                let frame_end = frame.map_or(end, |(_, frame_end)| frame_end.min(end));
+                if let Err(err) = self.flag_bits(start, frame_end) {
+                    return self.fatal(err);
+                }
+                self.position = frame_end;
+                continue;
+            }
+            let Some((header, frame_end)) = frame.filter(|&(_, frame_end)| frame_end <= end) else {
+                match self.incomplete(start, end, input_end) {
+                    Ok(true) => continue,
+                    Ok(false) => return None,
+                    Err(err) => return self.fatal(err),
+                }
+            };
+            if let Some(limit) = self.limit(frame_end, header) {
+                return self.fatal(UlogError {
+                    kind: UlogErrorKind::LimitExceeded(limit),
+                    offset: self.consumed + start,
+                    message_index: Some(self.message_index),
+                });
+            }
+            if is_corrupt_msg_type(header.msg_type) {
+                if self.options.mode == ParseMode::Strict {
+                    return self.fatal(UlogError {
+                        kind: UlogErrorKind::UnknownMessageType(header.msg_type.into()),
+                        offset: self.consumed + start + 2,
+                        message_index: Some(self.message_index),
+                    });
+                }
+                self.lost_sync = Some((self.consumed + start, header.msg_type));
+                self.position = start + 1;
+                continue;
+            }
+            self.position = frame_end;
+            if fixed_msg_size(header.msg_type).is_some_and(|size| header.msg_size > size) {
+                let kind = ParseWarningKind::SuspiciousSize {
+                    msg_type: header.msg_type,
+                    msg_size: header.msg_size,
+                };
+                self.warn(kind, self.consumed + start);
+            }
+            let body = &self.buffer[start + MESSAGE_HEADER_SIZE..frame_end];
+            if header.msg_type == MsgType::Data
+                && (self.options.metadata_only || self.is_filtered(body))
+            {
+                if self.options.metadata_only {
+                    let msg_id = body.get(..2).map(|id| u16::from_le_bytes([id[0], id[1]]));
+                    if let Some(msg_id) = msg_id.and_then(|msg_id| self.orphan(msg_id)) {
+                        *self.orphan_counts.entry(msg_id).or_default() += 1;
+                    }
+                }
+                self.message_index += 1;
+                continue;
+            }
+            // Strings are validated first so that replaced invalid UTF-8 is still reported.
+            let utf8_policy = std::mem::replace(&mut self.options.utf8_policy, Utf8Policy::Error);
+            let mut result = message_body(body, header, &self.options);
+            self.options.utf8_policy = utf8_policy;
+            if let Err(nom::Err::Failure(ParseError {
+                input: position,
+                kind: UlogErrorKind::InvalidUtf8(_),
+                ..
+            })) = result
+            {
+                if utf8_policy != Utf8Policy::Error {
+                    let offset =
+                        self.consumed + start + MESSAGE_HEADER_SIZE + body.offset(position);
+                    self.warn(ParseWarningKind::InvalidUtf8, offset);
+                    let body = &self.buffer[start + MESSAGE_HEADER_SIZE..frame_end];
+                    result = message_body(body, header, &self.options);
+                }
+            }
+            let mut message = match result {
+                Ok((_, message)) => message,
+                Err(nom::Err::Error(ParseError {
+                    kind: UlogErrorKind::UnknownMessageType(_),
+                    ..
+                })) => {
+                    self.unknown_messages.push(UnknownMessage {
+                        header,
+                        offset: self.consumed + start,
+                    });
+                    self.message_index += 1;
+                    continue;
+                }
+                Err(err) => {
+                    let err = self.error_at(err, start + MESSAGE_HEADER_SIZE);
+                    if self.options.mode == ParseMode::Strict {
+                        return self.fatal(err);
+                    }
+                    self.warn(ParseWarningKind::Recovered(err.kind), err.offset);
+                    self.message_index += 1;
+                    continue;
+                }
+            };
+            let orphan = match &message {
+                Message::Data(data) => self.orphan(data.msg_id),
+                _ => None,
+            };
+            if let Some(msg_id) = orphan {
+                *self.orphan_counts.entry(msg_id).or_default() += 1;
+                match self.options.orphan_policy {
+                    OrphanPolicy::Keep => {}
+                    OrphanPolicy::Drop => {
+                        self.message_index += 1;
+                        continue;
+                    }
+                    OrphanPolicy::Error => {
+                        return self.fatal(UlogError {
+                            kind: UlogErrorKind::OrphanData(msg_id),
+                            offset: self.consumed + start + MESSAGE_HEADER_SIZE,
+                            message_index: Some(self.message_index),
+                        });
+                    }
+                }
+            }
+            self.strings.intern_message(&mut message);
+            self.track(&message);
+            self.message_index += 1;
+            return Some(Ok(message));
+        }
+    }
+
+    fn is_filtered(&self, body: &[u8]) -> bool {
+        if self.options.topic_filter == TopicFilter::All {
+            return false;
+        }
+        let Some(&[low, high]) = body.get(..2) else {
+            return false;
+        };
+        self.subscriptions
+            .get(u16::from_le_bytes([low, high]))
+            .is_some_and(|subscription| {
+                !self.options.topic_filter.allows(&subscription.message_name)
+            })
+    }
+
+    // The msg_id of data that no AddLogged subscribed.
+    fn orphan(&self, msg_id: u16) -> Option<u16> {
+        self.subscriptions.get(msg_id).is_none().then_some(msg_id)
+    }
+
+    fn track(&mut self, message: &Message) {
+        match message {
+            Message::Format(format) => {
+                if let Ok(definition) = format.definition() {
+                    self.decoder.formats.insert(definition);
+                }
+            }
+            Message::AddLogged(add_logged) => {
+                self.subscriptions
+                    .add_logged(add_logged, self.message_index);
+                let _ = self.decoder.add_logged(add_logged);
+            }
+            Message::RemoveLogged(remove_logged) => {
+                self.subscriptions
+                    .remove_logged(remove_logged.msg_id, self.message_index);
+                self.decoder.remove_logged(remove_logged.msg_id);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Feed<'a> {
+    parser: &'a mut UlogParser,
+}
+
+impl Iterator for Feed<'_> {
+    type Item = Result<Message, UlogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.next_message()
+    }
+}
@@ -0,0 +1,13 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub bytes: usize,
+    pub total_bytes: Option<usize>,
+    pub messages: usize,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> Option<f64> {
+        let total = self.total_bytes.filter(|&total| total > 0)?;
+        Some((self.bytes as f64 / total as f64).min(1.0))
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{ErrorKind, Read};
+
+use crate::{
+    Formats, Message, MessageData, ParseOptions, Progress, UlogError, UlogErrorKind, UlogParser,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct UlogReader<R> {
+    reader: R,
+    chunk: Vec<u8>,
+    done: bool,
+    total_bytes: Option<usize>,
+    pub parser: UlogParser,
+}
+
+impl<R: Read> UlogReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        UlogReader {
+            reader,
+            chunk: vec![0; CHUNK_SIZE],
+            done: false,
+            total_bytes: None,
+            parser: UlogParser::with_options(options),
+        }
+    }
+
+    pub fn with_total_bytes(mut self, total_bytes: usize) -> Self {
+        self.total_bytes = Some(total_bytes);
+        self
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.parser.offset(),
+            total_bytes: self.total_bytes,
+            messages: self.parser.message_count(),
+        }
+    }
+
+    pub fn formats(&self) -> &Formats {
+        self.parser.formats()
+    }
+
+    pub fn reset(&mut self, reader: R) -> R {
+        self.parser.reset();
+        self.done = false;
+        self.total_bytes = None;
+        std::mem::replace(&mut self.reader, reader)
+    }
+
+    pub fn topic(self, message_name: impl Into<String>) -> TopicMessages<R> {
+        TopicMessages {
+            reader: self,
+            message_name: message_name.into(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_chunk(&mut self) -> Result<usize, UlogError> {
+        loop {
+            match self.reader.read(&mut self.chunk) {
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                result => {
+                    return result.map_err(|err| UlogError {
+                        kind: UlogErrorKind::Io(err),
+                        offset: self.parser.offset() + self.parser.buffered(),
+                        message_index: None,
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for UlogReader<R> {
+    type Item = Result<Message, UlogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.parser.next_message() {
+                self.done |= result.is_err();
+                return Some(result);
+            }
+            if self.done {
+                return None;
+            }
+            match self.read_chunk() {
+                // Messages may still follow a resync past the leftover bytes.
+                Ok(0) => {
+                    self.done = true;
+                    if let Err(err) = self.parser.finish() {
+                        return Some(Err(err));
+                    }
+                }
+                Ok(read) => {
+                    let _ = self.parser.feed(&self.chunk[..read]);
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TopicMessages<R> {
+    pub reader: UlogReader<R>,
+    pub message_name: String,
+}
+
+impl<R: Read> Iterator for TopicMessages<R> {
+    type Item = Result<MessageData, UlogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.reader.next()? {
+                Ok(Message::Data(data)) => {
+                    let subscriptions = &self.reader.parser.subscriptions;
+                    if subscriptions.get(data.msg_id).is_some_and(|subscription| {
+                        *subscription.message_name == *self.message_name
+                    }) {
+                        return Some(Ok(data));
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
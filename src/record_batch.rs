@@ -0,0 +1,129 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arrow::{
+    array::{
+        ArrayRef, BooleanArray, FixedSizeListArray, Float32Array, Float64Array, Int16Array,
+        Int32Array, Int64Array, Int8Array, RecordBatch, StringArray, UInt16Array, UInt32Array,
+        UInt64Array, UInt8Array,
+    },
+    datatypes::{DataType, Field, Schema},
+    error::ArrowError,
+};
+
+use crate::{Column, ColumnData, Dataset, ExportError, FieldType, Layout, Ulog};
+
+fn data_type(field_type: &FieldType, array_len: Option<usize>) -> DataType {
+    let item = match field_type {
+        FieldType::Int8 => DataType::Int8,
+        FieldType::UInt8 => DataType::UInt8,
+        FieldType::Int16 => DataType::Int16,
+        FieldType::UInt16 => DataType::UInt16,
+        FieldType::Int32 => DataType::Int32,
+        FieldType::UInt32 => DataType::UInt32,
+        FieldType::Int64 => DataType::Int64,
+        FieldType::UInt64 => DataType::UInt64,
+        FieldType::Float => DataType::Float32,
+        FieldType::Double => DataType::Float64,
+        FieldType::Bool => DataType::Boolean,
+        // Nested fields are flattened by the layout, so only chars end up here.
+        FieldType::Char | FieldType::Nested(_) => return DataType::Utf8,
+    };
+    match array_len.and_then(|len| i32::try_from(len).ok()) {
+        Some(len) => DataType::FixedSizeList(Arc::new(Field::new("item", item, false)), len),
+        None => item,
+    }
+}
+
+fn schema(name: &str, multi_id: u8, fields: Vec<Field>) -> Schema {
+    let metadata = HashMap::from([
+        ("ulog.message_name".to_string(), name.to_string()),
+        ("ulog.multi_id".to_string(), multi_id.to_string()),
+    ]);
+    Schema::new_with_metadata(fields, metadata)
+}
+
+// Array fields become fixed-size lists and char arrays strings; padding is left out.
+fn column_array(column: Column) -> Result<(Field, ArrayRef), ArrowError> {
+    let field = Field::new(
+        &column.name,
+        data_type(&column.field_type, column.array_len),
+        false,
+    );
+    let values: ArrayRef = match column.data {
+        ColumnData::Int8(v) => Arc::new(Int8Array::from(v)),
+        ColumnData::UInt8(v) => Arc::new(UInt8Array::from(v)),
+        ColumnData::Int16(v) => Arc::new(Int16Array::from(v)),
+        ColumnData::UInt16(v) => Arc::new(UInt16Array::from(v)),
+        ColumnData::Int32(v) => Arc::new(Int32Array::from(v)),
+        ColumnData::UInt32(v) => Arc::new(UInt32Array::from(v)),
+        ColumnData::Int64(v) => Arc::new(Int64Array::from(v)),
+        ColumnData::UInt64(v) => Arc::new(UInt64Array::from(v)),
+        ColumnData::Float(v) => Arc::new(Float32Array::from(v)),
+        ColumnData::Double(v) => Arc::new(Float64Array::from(v)),
+        ColumnData::Bool(v) => Arc::new(BooleanArray::from(v)),
+        ColumnData::Char(v) => {
+            Arc::new(StringArray::from_iter_values(v.iter().map(char::to_string)))
+        }
+        ColumnData::String(v) => Arc::new(StringArray::from(v)),
+    };
+    match field.data_type() {
+        DataType::FixedSizeList(item, len) => {
+            let list = FixedSizeListArray::try_new(item.clone(), *len, values, None)?;
+            Ok((field, Arc::new(list)))
+        }
+        _ => Ok((field, values)),
+    }
+}
+
+impl Layout {
+    pub fn arrow_schema(&self, multi_id: u8) -> Schema {
+        let fields = self
+            .fields
+            .iter()
+            .filter(|field| !field.name.starts_with("_padding"))
+            .map(|field| {
+                let data_type = data_type(&field.field_type, field.array_len);
+                Field::new(&field.name, data_type, false)
+            })
+            .collect();
+        schema(&self.name, multi_id, fields)
+    }
+}
+
+impl Dataset {
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        self.clone().into_record_batch()
+    }
+
+    // Moves the column buffers into the batch without copying them.
+    pub fn into_record_batch(self) -> Result<RecordBatch, ArrowError> {
+        let (fields, columns): (Vec<_>, Vec<_>) = self
+            .columns
+            .into_iter()
+            .filter(|column| !column.name.starts_with("_padding"))
+            .map(column_array)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+        let schema = schema(&self.name, self.multi_id, fields);
+        RecordBatch::try_new(Arc::new(schema), columns)
+    }
+}
+
+impl Ulog {
+    pub fn record_batch(
+        &self,
+        message_name: &str,
+        multi_id: u8,
+    ) -> Result<RecordBatch, ExportError> {
+        Ok(self.dataset(message_name, multi_id)?.into_record_batch()?)
+    }
+
+    // One batch per topic instance, ordered by name and multi_id.
+    pub fn record_batches(&self) -> Result<Vec<RecordBatch>, ExportError> {
+        self.topic_instances()
+            .into_iter()
+            .map(|(message_name, multi_id)| self.record_batch(&message_name, multi_id))
+            .collect()
+    }
+}
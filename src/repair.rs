@@ -0,0 +1,64 @@
+use crate::{
+    parse_ulog_with_options, ParseMode, ParseOptions, ParseWarning, ParseWarningKind, UlogError,
+    UlogWriter,
+};
+
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    pub recovered_messages: usize,
+    pub rejected_messages: usize,
+    pub orphan_messages: usize,
+    pub unknown_messages: usize,
+    pub skipped_bytes: usize,
+    pub trailing_bytes: usize,
+    pub next_log_offset: Option<usize>,
+    pub truncation: Option<UlogError>,
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl RepairReport {
+    pub fn is_lossless(&self) -> bool {
+        self.rejected_messages == 0
+            && self.orphan_messages == 0
+            && self.unknown_messages == 0
+            && self.skipped_bytes == 0
+            && self.trailing_bytes == 0
+            && self.truncation.is_none()
+    }
+}
+
+// Parses `input` leniently and writes back everything the writer accepts. Only the
+// first log of a concatenated file is repaired, and an unreadable header is an error.
+pub fn repair(input: &[u8]) -> Result<(Vec<u8>, RepairReport), UlogError> {
+    let options = ParseOptions::new().mode(ParseMode::Lenient);
+    let ulog = parse_ulog_with_options(input, &options)?;
+    let mut report = RepairReport {
+        orphan_messages: ulog.orphan_counts.values().sum(),
+        unknown_messages: ulog.unknown_messages.len(),
+        trailing_bytes: ulog.trailing_bytes.map_or(0, |trailing| trailing.len),
+        next_log_offset: ulog.next_log_offset,
+        ..RepairReport::default()
+    };
+
+    let mut writer =
+        UlogWriter::new(Vec::new(), ulog.header.timestamp).expect("writing to a Vec cannot fail");
+    for message in ulog.definitions.iter().chain(&ulog.data) {
+        if writer.write_message(message).is_ok() {
+            report.recovered_messages += 1;
+        } else {
+            report.rejected_messages += 1;
+        }
+    }
+
+    report.skipped_bytes = ulog
+        .warnings
+        .iter()
+        .map(|warning| match warning.kind {
+            ParseWarningKind::SyncLost { skipped_bytes } => skipped_bytes,
+            _ => 0,
+        })
+        .sum();
+    report.truncation = ulog.truncation;
+    report.warnings = ulog.warnings;
+    Ok((writer.into_inner(), report))
+}
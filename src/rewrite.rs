@@ -0,0 +1,359 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    error::Error,
+    fmt,
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use crate::{
+    msg_ids::MsgIdSet, FieldType, FormatError, Formats, Message, MessageData, MessageDataRef,
+    TopicFilter, Ulog, INCOMPAT_FLAG0_DATA_APPENDED_MASK,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decimation {
+    EveryNth(usize),
+    MaxRate(f64),
+}
+
+impl Decimation {
+    fn is_valid(&self) -> bool {
+        match *self {
+            Decimation::EveryNth(n) => n > 0,
+            Decimation::MaxRate(rate) => rate.is_finite() && rate > 0.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DownsampleError {
+    InvalidDecimation(Decimation),
+    Format(FormatError),
+}
+
+impl fmt::Display for DownsampleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownsampleError::InvalidDecimation(Decimation::EveryNth(n)) => {
+                write!(f, "cannot keep every {}th sample, n must be at least 1", n)
+            }
+            DownsampleError::InvalidDecimation(Decimation::MaxRate(rate)) => {
+                write!(
+                    f,
+                    "maximum rate {} Hz is not a positive finite number",
+                    rate
+                )
+            }
+            DownsampleError::Format(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for DownsampleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DownsampleError::InvalidDecimation(_) => None,
+            DownsampleError::Format(err) => Some(err),
+        }
+    }
+}
+
+impl From<FormatError> for DownsampleError {
+    fn from(err: FormatError) -> Self {
+        DownsampleError::Format(err)
+    }
+}
+
+#[derive(Default)]
+struct DecimationState {
+    seen: usize,
+    last_kept: Option<u64>,
+}
+
+impl DecimationState {
+    fn keep(&mut self, decimation: Decimation, timestamp: Option<u64>) -> bool {
+        let keep = match (decimation, timestamp, self.last_kept) {
+            (Decimation::EveryNth(n), ..) => self.seen.is_multiple_of(n),
+            (Decimation::MaxRate(rate), Some(timestamp), Some(last_kept)) => {
+                (timestamp.saturating_sub(last_kept) as f64) >= 1e6 / rate
+            }
+            (Decimation::MaxRate(_), ..) => true,
+        };
+        self.seen += 1;
+        if keep {
+            self.last_kept = timestamp.or(self.last_kept);
+        }
+        keep
+    }
+}
+
+// Follows subscriptions so the timestamp of each data message can be read.
+pub(crate) struct Timestamps {
+    formats: Formats,
+    offsets: HashMap<Arc<str>, Option<usize>>,
+    active: HashMap<u16, Option<usize>>,
+}
+
+impl Timestamps {
+    pub(crate) fn new(ulog: &Ulog) -> Result<Self, FormatError> {
+        Ok(Timestamps {
+            formats: ulog.formats()?,
+            offsets: HashMap::new(),
+            active: HashMap::new(),
+        })
+    }
+
+    pub(crate) fn update(&mut self, message: &Message) {
+        match message {
+            Message::AddLogged(add_logged) => {
+                let formats = &self.formats;
+                let offset = *self
+                    .offsets
+                    .entry(add_logged.message_name.clone())
+                    .or_insert_with(|| {
+                        let layout = formats.layout(&add_logged.message_name).ok()?;
+                        layout.timestamp_field().map(|field| field.offset)
+                    });
+                self.active.insert(add_logged.msg_id, offset);
+            }
+            Message::RemoveLogged(remove_logged) => {
+                self.active.remove(&remove_logged.msg_id);
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn data(&self, data: &MessageData) -> Option<u64> {
+        let offset = (*self.active.get(&data.msg_id)?)?;
+        let bytes = data.data.get(offset..offset + 8)?;
+        Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub(crate) fn message(&self, message: &Message) -> Option<u64> {
+        match message {
+            Message::Data(data) => self.data(data),
+            Message::Logging(logging) => Some(logging.timestamp),
+            Message::LoggingTagged(logging) => Some(logging.timestamp),
+            _ => None,
+        }
+    }
+}
+
+fn is_after(range: &impl RangeBounds<u64>, timestamp: u64) -> bool {
+    match range.end_bound() {
+        Bound::Included(&end) => timestamp > end,
+        Bound::Excluded(&end) => timestamp >= end,
+        Bound::Unbounded => false,
+    }
+}
+
+// The given formats and every format nested inside them.
+fn referenced<'a>(formats: &Formats, names: impl IntoIterator<Item = &'a str>) -> HashSet<String> {
+    let mut referenced = HashSet::new();
+    let mut pending: Vec<String> = names.into_iter().map(str::to_string).collect();
+    while let Some(name) = pending.pop() {
+        if let Some(definition) = formats.get(&name) {
+            for field in &definition.fields {
+                if let FieldType::Nested(nested) = &field.field_type {
+                    pending.push(nested.clone());
+                }
+            }
+        }
+        referenced.insert(name);
+    }
+    referenced
+}
+
+impl Ulog {
+    // Offsets into the source file no longer apply, so appended-data markers are cleared.
+    pub(crate) fn rewritten(&self, definitions: Vec<Message>, data: Vec<Message>) -> Ulog {
+        let mut message_flag_bits = self.message_flag_bits.clone();
+        message_flag_bits.incompat_flags[0] &= !INCOMPAT_FLAG0_DATA_APPENDED_MASK;
+        message_flag_bits.appended_offsets = [0; 3];
+
+        Ulog {
+            header: self.header.clone(),
+            message_flag_bits,
+            definitions,
+            data,
+            unknown_messages: Vec::new(),
+            warnings: Vec::new(),
+            truncation: None,
+            trailing_bytes: None,
+            orphan_data: Vec::new(),
+            orphan_counts: BTreeMap::new(),
+            next_log_offset: None,
+            cancelled: false,
+            data_summary: BTreeMap::new(),
+        }
+    }
+
+    // Subscriptions are always kept. Parameter and info changes are kept up to the
+    // end of the window so the state at its start stays correct, and messages
+    // without a timestamp of their own follow the last timestamp seen.
+    pub fn trim(&self, range: impl RangeBounds<u64>) -> Result<Ulog, FormatError> {
+        let mut timestamps = Timestamps::new(self)?;
+        let mut current = None;
+        let mut data = Vec::new();
+        for message in &self.data {
+            timestamps.update(message);
+            let timestamp = timestamps.message(message);
+            if timestamp.is_some() {
+                current = timestamp;
+            }
+            let keep = match message {
+                Message::Format(_) | Message::AddLogged(_) | Message::RemoveLogged(_) => true,
+                Message::Info(_)
+                | Message::InfoMultiple(_)
+                | Message::Parameter(_)
+                | Message::ParameterDefault(_) => {
+                    !current.is_some_and(|timestamp| is_after(&range, timestamp))
+                }
+                _ => range.contains(&current.unwrap_or(0)),
+            };
+            if keep {
+                data.push(message.clone());
+            }
+        }
+        Ok(self.rewritten(self.definitions.clone(), data))
+    }
+
+    // Formats only used by the removed topics are dropped along with them.
+    pub fn filter_topics(&self, filter: &TopicFilter) -> Result<Ulog, FormatError> {
+        let formats = self.formats()?;
+        let mut dropped = MsgIdSet::default();
+        let mut subscribed = HashSet::new();
+        let mut kept = HashSet::new();
+        let mut data = Vec::new();
+        for message in &self.data {
+            let keep = match message {
+                Message::AddLogged(add_logged) => {
+                    subscribed.insert(add_logged.message_name.clone());
+                    if filter.allows(&add_logged.message_name) {
+                        kept.insert(add_logged.message_name.clone());
+                        dropped.remove(add_logged.msg_id);
+                        true
+                    } else {
+                        dropped.insert(add_logged.msg_id);
+                        false
+                    }
+                }
+                Message::RemoveLogged(remove_logged) if dropped.contains(remove_logged.msg_id) => {
+                    dropped.remove(remove_logged.msg_id);
+                    false
+                }
+                Message::Data(message_data) => !dropped.contains(message_data.msg_id),
+                Message::DataRef(data_ref) => !dropped.contains(data_ref.msg_id),
+                _ => true,
+            };
+            if keep {
+                data.push(message.clone());
+            }
+        }
+
+        let unused = referenced(&formats, subscribed.iter().map(|name| &**name));
+        let used = referenced(&formats, kept.iter().map(|name| &**name));
+        let definitions = self
+            .definitions
+            .iter()
+            .filter(|message| match message {
+                Message::Format(format) => format.definition().is_ok_and(|definition| {
+                    used.contains(&definition.name) || !unused.contains(&definition.name)
+                }),
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        Ok(self.rewritten(definitions, data))
+    }
+
+    // Applies to each topic instance separately. Samples without a timestamp are
+    // always kept under a maximum rate.
+    pub fn downsample(&self, decimation: Decimation) -> Result<Ulog, DownsampleError> {
+        if !decimation.is_valid() {
+            return Err(DownsampleError::InvalidDecimation(decimation));
+        }
+        let mut timestamps = Timestamps::new(self)?;
+        let mut states: HashMap<u16, DecimationState> = HashMap::new();
+        let mut data = Vec::new();
+        for message in &self.data {
+            timestamps.update(message);
+            let keep = match message {
+                Message::AddLogged(add_logged) => {
+                    states.insert(add_logged.msg_id, DecimationState::default());
+                    true
+                }
+                Message::Data(message_data) => states
+                    .entry(message_data.msg_id)
+                    .or_default()
+                    .keep(decimation, timestamps.data(message_data)),
+                Message::DataRef(data_ref) => states
+                    .entry(data_ref.msg_id)
+                    .or_default()
+                    .keep(decimation, None),
+                _ => true,
+            };
+            if keep {
+                data.push(message.clone());
+            }
+        }
+        Ok(self.rewritten(self.definitions.clone(), data))
+    }
+
+    // Each subscription takes the lowest msg_id free at that point, so ids released by
+    // a remove are reused.
+    pub fn renumber(&self) -> Ulog {
+        let mut remap: HashMap<u16, u16> = HashMap::new();
+        let mut released = BTreeSet::new();
+        let mut next = 0u16;
+        let mut data = Vec::with_capacity(self.data.len());
+        for message in &self.data {
+            let message = match message {
+                Message::AddLogged(add_logged) => {
+                    if let Some(previous) = remap.remove(&add_logged.msg_id) {
+                        released.insert(previous);
+                    }
+                    let msg_id = released.pop_first().unwrap_or_else(|| {
+                        let msg_id = next;
+                        next = next.wrapping_add(1);
+                        msg_id
+                    });
+                    remap.insert(add_logged.msg_id, msg_id);
+                    let mut add_logged = add_logged.clone();
+                    add_logged.msg_id = msg_id;
+                    Message::AddLogged(add_logged)
+                }
+                Message::RemoveLogged(remove_logged) => {
+                    let Some(msg_id) = remap.remove(&remove_logged.msg_id) else {
+                        continue;
+                    };
+                    released.insert(msg_id);
+                    let mut remove_logged = remove_logged.clone();
+                    remove_logged.msg_id = msg_id;
+                    Message::RemoveLogged(remove_logged)
+                }
+                Message::Data(message_data) => {
+                    let Some(&msg_id) = remap.get(&message_data.msg_id) else {
+                        continue;
+                    };
+                    let mut message_data = message_data.clone();
+                    message_data.msg_id = msg_id;
+                    Message::Data(message_data)
+                }
+                Message::DataRef(data_ref) => {
+                    let Some(&msg_id) = remap.get(&data_ref.msg_id) else {
+                        continue;
+                    };
+                    Message::DataRef(MessageDataRef {
+                        msg_id,
+                        ..*data_ref
+                    })
+                }
+                message => message.clone(),
+            };
+            data.push(message);
+        }
+        self.rewritten(self.definitions.clone(), data)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{FieldType, FormatError, Formats};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldSchema {
+    pub name: String,
+    pub type_name: String,
+    pub array_len: Option<usize>,
+    pub offset: usize,
+    pub size: usize,
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TopicSchema {
+    pub name: String,
+    pub size: usize,
+    pub fields: Vec<FieldSchema>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Schema {
+    pub topics: Vec<TopicSchema>,
+}
+
+impl Schema {
+    pub fn new(formats: &Formats) -> Result<Self, FormatError> {
+        let mut names: Vec<&String> = formats.definitions.keys().collect();
+        names.sort();
+        let topics = names
+            .into_iter()
+            .map(|name| {
+                let size = formats.layout(name)?.size;
+                Ok(TopicSchema {
+                    name: name.clone(),
+                    size,
+                    fields: field_schemas(formats, name)?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Schema { topics })
+    }
+
+    pub fn topic(&self, name: &str) -> Option<&TopicSchema> {
+        self.topics.iter().find(|topic| topic.name == name)
+    }
+}
+
+fn field_schemas(formats: &Formats, name: &str) -> Result<Vec<FieldSchema>, FormatError> {
+    let mut offset = 0;
+    let mut fields = Vec::new();
+    let Some(definition) = formats.get(name) else {
+        return Ok(fields);
+    };
+    for field in &definition.fields {
+        let (type_name, element_size, nested) = match &field.field_type {
+            FieldType::Nested(nested) => (
+                nested.clone(),
+                formats.layout(nested)?.size,
+                field_schemas(formats, nested)?,
+            ),
+            field_type => (
+                field_type.name().to_string(),
+                field_type.size().unwrap_or(0),
+                Vec::new(),
+            ),
+        };
+        let size = element_size
+            .checked_mul(field.array_len.unwrap_or(1))
+            .ok_or_else(|| FormatError::TooLarge(name.to_string()))?;
+        if !field.is_padding() {
+            fields.push(FieldSchema {
+                name: field.name.clone(),
+                type_name,
+                array_len: field.array_len,
+                offset,
+                size,
+                fields: nested,
+            });
+        }
+        offset = offset
+            .checked_add(size)
+            .ok_or_else(|| FormatError::TooLarge(name.to_string()))?;
+    }
+    Ok(fields)
+}
@@ -0,0 +1,153 @@
+use std::io::Write;
+
+use crate::{Message, MessageFlagBits, MessageHeader, MsgType, Ulog, WriteError, MAGIC};
+
+fn encode_body(message: &Message, output: &mut Vec<u8>) -> Result<(), WriteError> {
+    match message {
+        Message::Format(format) => output.extend_from_slice(format.format.as_bytes()),
+        Message::Info(info) => {
+            output.push(info.key.len() as u8);
+            output.extend_from_slice(info.key.as_bytes());
+            output.extend_from_slice(&info.value);
+        }
+        Message::InfoMultiple(info) => {
+            output.push(info.is_continued);
+            output.push(info.key.len() as u8);
+            output.extend_from_slice(info.key.as_bytes());
+            output.extend_from_slice(&info.value);
+        }
+        Message::Parameter(parameter) => {
+            output.push(parameter.key.len() as u8);
+            output.extend_from_slice(parameter.key.as_bytes());
+            output.extend_from_slice(&parameter.value);
+        }
+        Message::ParameterDefault(parameter) => {
+            output.push(parameter.default_types.bits());
+            output.push(parameter.key.len() as u8);
+            output.extend_from_slice(parameter.key.as_bytes());
+            output.extend_from_slice(&parameter.value);
+        }
+        Message::AddLogged(add_logged) => {
+            output.push(add_logged.multi_id);
+            output.extend_from_slice(&add_logged.msg_id.to_le_bytes());
+            output.extend_from_slice(add_logged.message_name.as_bytes());
+        }
+        Message::RemoveLogged(remove_logged) => {
+            output.extend_from_slice(&remove_logged.msg_id.to_le_bytes())
+        }
+        Message::Data(data) => {
+            output.extend_from_slice(&data.msg_id.to_le_bytes());
+            output.extend_from_slice(&data.data);
+        }
+        Message::DataRef(data) => return Err(WriteError::UnresolvedDataRef(data.msg_id)),
+        Message::Logging(logging) => {
+            output.push(logging.log_level);
+            output.extend_from_slice(&logging.timestamp.to_le_bytes());
+            output.extend_from_slice(logging.message.as_bytes());
+        }
+        Message::LoggingTagged(logging) => {
+            output.push(logging.log_level);
+            output.extend_from_slice(&logging.tag.to_le_bytes());
+            output.extend_from_slice(&logging.timestamp.to_le_bytes());
+            output.extend_from_slice(logging.message.as_bytes());
+        }
+        Message::Sync(sync) => output.extend_from_slice(&sync.sync_magic),
+        Message::Dropout(dropout) => output.extend_from_slice(&dropout.duration.to_le_bytes()),
+        Message::Raw(raw) => output.extend_from_slice(&raw.data),
+    }
+    Ok(())
+}
+
+fn key_fits(message: &Message) -> Result<(), WriteError> {
+    let key = match message {
+        Message::Info(info) => &info.key,
+        Message::InfoMultiple(info) => &info.key,
+        Message::Parameter(parameter) => &parameter.key,
+        Message::ParameterDefault(parameter) => &parameter.key,
+        _ => return Ok(()),
+    };
+    match key.len() {
+        0..=255 => Ok(()),
+        _ => Err(WriteError::KeyTooLong(key.to_string())),
+    }
+}
+
+// Bytes declared by msg_size beyond the known fields are written back as zeros.
+fn encode_frame(
+    header: MessageHeader,
+    output: &mut Vec<u8>,
+    body: impl FnOnce(&mut Vec<u8>) -> Result<(), WriteError>,
+) -> Result<(), WriteError> {
+    let start = output.len();
+    output.extend_from_slice(&[0; 3]);
+    let frame = body(output).and_then(|()| {
+        let len = output.len() - start - 3;
+        MessageHeader::new(header.msg_type, len.max(header.msg_size.into()))
+    });
+    let frame = match frame {
+        Ok(frame) => frame,
+        Err(err) => {
+            output.truncate(start);
+            return Err(err);
+        }
+    };
+    output.resize(start + 3 + usize::from(frame.msg_size), 0);
+    output[start..start + 2].copy_from_slice(&frame.msg_size.to_le_bytes());
+    output[start + 2] = frame.msg_type.into();
+    Ok(())
+}
+
+impl Message {
+    pub fn encode(&self, output: &mut Vec<u8>) -> Result<(), WriteError> {
+        key_fits(self)?;
+        encode_frame(self.header(), output, |output| encode_body(self, output))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        let mut output = Vec::new();
+        self.encode(&mut output)?;
+        Ok(output)
+    }
+}
+
+impl MessageFlagBits {
+    pub fn encode(&self, output: &mut Vec<u8>) -> Result<(), WriteError> {
+        let header = MessageHeader {
+            msg_type: MsgType::FlagBits,
+            ..self.header
+        };
+        encode_frame(header, output, |output| {
+            output.extend_from_slice(&self.compat_flags);
+            output.extend_from_slice(&self.incompat_flags);
+            for offset in self.appended_offsets {
+                output.extend_from_slice(&offset.to_le_bytes());
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Ulog {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        let mut output = Vec::new();
+        self.write_to(&mut output)?;
+        Ok(output)
+    }
+
+    // Streams the log message by message instead of building it in memory.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), WriteError> {
+        let mut output = MAGIC.to_vec();
+        output.push(self.header.version);
+        output.extend_from_slice(&self.header.timestamp.to_le_bytes());
+        self.message_flag_bits.encode(&mut output)?;
+        for message in self.definitions.iter().chain(&self.data) {
+            message.encode(&mut output)?;
+            if output.len() >= 64 * 1024 {
+                writer.write_all(&output)?;
+                output.clear();
+            }
+        }
+        writer.write_all(&output)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    header,
+    index::{edge_hash, log_hash, HASHED_LEN},
+    FormatDefinition, IndexEntry, TopicIndex, UlogError, UlogErrorKind, UlogIndex,
+};
+
+const SIDECAR_MAGIC: [u8; 7] = *b"ULogIdx";
+const SIDECAR_VERSION: u8 = 2;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn io_error(err: io::Error) -> UlogError {
+    UlogError {
+        kind: UlogErrorKind::Io(err),
+        offset: 0,
+        message_index: None,
+    }
+}
+
+// Integers are LEB128 varints; entry offsets and timestamps are stored as deltas to stay small.
+fn put_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn put_string(output: &mut Vec<u8>, string: &str) {
+    put_varint(output, string.len() as u64);
+    output.extend_from_slice(string.as_bytes());
+}
+
+fn zigzag(delta: i64) -> u64 {
+    ((delta << 1) ^ (delta >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+struct Cursor<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.input.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid_data("varint is too long"))
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        usize::try_from(self.varint()?).map_err(|_| invalid_data("length does not fit in memory"))
+    }
+
+    fn string(&mut self) -> io::Result<&'a str> {
+        let len = self.len()?;
+        std::str::from_utf8(self.bytes(len)?).map_err(|_| invalid_data("string is not UTF-8"))
+    }
+}
+
+impl UlogIndex {
+    pub fn sidecar_path(log_path: impl AsRef<Path>) -> PathBuf {
+        log_path.as_ref().with_extension("ulgidx")
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = SIDECAR_MAGIC.to_vec();
+        output.push(SIDECAR_VERSION);
+        put_varint(&mut output, self.log_len as u64);
+        put_varint(&mut output, self.log_timestamp);
+        output.extend_from_slice(&self.log_hash.to_le_bytes());
+        put_varint(&mut output, self.indexed_bytes as u64);
+        let mut definitions: Vec<&FormatDefinition> = self.formats.definitions.values().collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        put_varint(&mut output, definitions.len() as u64);
+        for definition in definitions {
+            put_string(&mut output, &definition.to_string());
+        }
+        put_varint(&mut output, self.topics.len() as u64);
+        for topic in &self.topics {
+            put_string(&mut output, &topic.message_name);
+            output.push(topic.multi_id);
+            put_varint(&mut output, topic.entries.len() as u64);
+            let (mut offset, mut timestamp) = (0, 0);
+            for entry in &topic.entries {
+                put_varint(&mut output, (entry.offset - offset) as u64);
+                put_varint(&mut output, entry.msg_id.into());
+                match entry.timestamp {
+                    Some(value) => {
+                        put_varint(
+                            &mut output,
+                            zigzag(value.wrapping_sub(timestamp) as i64) + 1,
+                        );
+                        timestamp = value;
+                    }
+                    None => put_varint(&mut output, 0),
+                }
+                offset = entry.offset;
+            }
+        }
+        put_varint(&mut output, self.sync_offsets.len() as u64);
+        let mut offset = 0;
+        for &sync_offset in &self.sync_offsets {
+            put_varint(&mut output, (sync_offset - offset) as u64);
+            offset = sync_offset;
+        }
+        output
+    }
+
+    pub fn from_bytes(input: &[u8]) -> io::Result<Self> {
+        let mut cursor = Cursor { input };
+        if cursor.bytes(SIDECAR_MAGIC.len())? != SIDECAR_MAGIC {
+            return Err(invalid_data("not a ULog index file"));
+        }
+        if cursor.byte()? != SIDECAR_VERSION {
+            return Err(invalid_data("unsupported ULog index version"));
+        }
+        let mut index = UlogIndex {
+            log_len: cursor.len()?,
+            log_timestamp: cursor.varint()?,
+            log_hash: u64::from_le_bytes(cursor.bytes(8)?.try_into().unwrap()),
+            indexed_bytes: cursor.len()?,
+            ..Self::default()
+        };
+        for _ in 0..cursor.len()? {
+            let definition = FormatDefinition::parse(cursor.string()?)
+                .map_err(|_| invalid_data("invalid format definition"))?;
+            index.formats.insert(definition);
+        }
+        for _ in 0..cursor.len()? {
+            let message_name = cursor.string()?.to_string();
+            let multi_id = cursor.byte()?;
+            let len = cursor.len()?;
+            let mut entries = Vec::with_capacity(len.min(cursor.input.len()));
+            let (mut offset, mut timestamp) = (0usize, 0u64);
+            for _ in 0..len {
+                offset = offset
+                    .checked_add(cursor.len()?)
+                    .ok_or_else(|| invalid_data("offset overflow"))?;
+                let msg_id = u16::try_from(cursor.varint()?)
+                    .map_err(|_| invalid_data("msg_id out of range"))?;
+                let entry_timestamp = match cursor.varint()? {
+                    0 => None,
+                    delta => {
+                        timestamp = timestamp.wrapping_add(unzigzag(delta - 1) as u64);
+                        Some(timestamp)
+                    }
+                };
+                entries.push(IndexEntry {
+                    offset,
+                    msg_id,
+                    timestamp: entry_timestamp,
+                });
+            }
+            index.topics.push(TopicIndex {
+                message_name,
+                multi_id,
+                entries,
+            });
+        }
+        let mut offset = 0usize;
+        for _ in 0..cursor.len()? {
+            offset = offset
+                .checked_add(cursor.len()?)
+                .ok_or_else(|| invalid_data("offset overflow"))?;
+            index.sync_offsets.push(offset);
+        }
+        Ok(index)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let mut input = Vec::new();
+        reader.read_to_end(&mut input)?;
+        Self::from_bytes(&input)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn matches(&self, input: &[u8]) -> bool {
+        self.log_len == input.len()
+            && header(input).is_ok_and(|(_, header)| header.timestamp == self.log_timestamp)
+            && log_hash(input) == self.log_hash
+    }
+
+    // Checks the size, header and hashed bytes of the log without reading all of it.
+    pub fn matches_file(&self, log_path: impl AsRef<Path>) -> io::Result<bool> {
+        let mut file = File::open(log_path)?;
+        if usize::try_from(file.metadata()?.len()) != Ok(self.log_len) {
+            return Ok(false);
+        }
+        let mut head = vec![0; self.log_len.min(HASHED_LEN)];
+        file.read_exact(&mut head)?;
+        if !header(&head).is_ok_and(|(_, header)| header.timestamp == self.log_timestamp) {
+            return Ok(false);
+        }
+        let mut tail = vec![0; head.len()];
+        file.seek(SeekFrom::End(-(tail.len() as i64)))?;
+        file.read_exact(&mut tail)?;
+        Ok(edge_hash(&head, &tail) == self.log_hash)
+    }
+
+    // Reuses the sidecar index if it still matches the log, otherwise indexes the log
+    // and writes the sidecar. The sidecar is only a cache, so the index is returned
+    // even if it cannot be written, e.g. next to a log on a read-only mount.
+    pub fn open(log_path: impl AsRef<Path>) -> Result<Self, UlogError> {
+        let log_path = log_path.as_ref();
+        let sidecar_path = Self::sidecar_path(log_path);
+        if let Ok(index) = Self::load(&sidecar_path) {
+            if index.matches_file(log_path).map_err(io_error)? {
+                return Ok(index);
+            }
+        }
+        let input = fs::read(log_path).map_err(io_error)?;
+        let index = Self::build(&input)?;
+        if let Err(_err) = index.save(&sidecar_path) {
+            warn!("cannot write index {}: {}", sidecar_path.display(), _err);
+        }
+        Ok(index)
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, mem, sync::Arc, time::Duration};
+
+use crate::{rewrite::Timestamps, FormatError, Message, MessageAddLogged, MessageParameter, Ulog};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLimit {
+    Duration(Duration),
+    Bytes(usize),
+}
+
+fn encoded_len(message: &Message) -> usize {
+    3 + usize::from(message.header().msg_size)
+}
+
+struct Segment {
+    timestamp: u64,
+    start: Option<u64>,
+    bytes: usize,
+    has_data: bool,
+    definitions: Vec<Message>,
+    data: Vec<Message>,
+}
+
+impl Segment {
+    fn new(ulog: &Ulog, timestamp: u64, definitions: Vec<Message>, data: Vec<Message>) -> Self {
+        let bytes = 16
+            + 3
+            + usize::from(ulog.message_flag_bits.header.msg_size)
+            + definitions.iter().map(encoded_len).sum::<usize>()
+            + data.iter().map(encoded_len).sum::<usize>();
+        Segment {
+            timestamp,
+            start: None,
+            bytes,
+            has_data: false,
+            definitions,
+            data,
+        }
+    }
+
+    fn is_full(&self, limit: SplitLimit, message: &Message, timestamp: Option<u64>) -> bool {
+        if !self.has_data {
+            return false;
+        }
+        match limit {
+            SplitLimit::Duration(duration) => {
+                let duration = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+                timestamp
+                    .zip(self.start)
+                    .is_some_and(|(timestamp, start)| timestamp.saturating_sub(start) >= duration)
+            }
+            SplitLimit::Bytes(bytes) => self.bytes + encoded_len(message) > bytes,
+        }
+    }
+
+    fn push(&mut self, message: &Message, timestamp: Option<u64>) {
+        if self.start.is_none() {
+            self.start = timestamp;
+        }
+        if let Message::Data(_) | Message::DataRef(_) = message {
+            self.has_data = true;
+        }
+        self.bytes += encoded_len(message);
+        self.data.push(message.clone());
+    }
+}
+
+// Definitions with the parameters replaced by their current values.
+fn current_definitions(
+    ulog: &Ulog,
+    parameters: &BTreeMap<Arc<str>, MessageParameter>,
+) -> Vec<Message> {
+    let mut pending = parameters.clone();
+    let mut definitions: Vec<Message> = ulog
+        .definitions
+        .iter()
+        .map(|message| match message {
+            Message::Parameter(parameter) => match pending.remove(&parameter.key) {
+                Some(current) => Message::Parameter(current),
+                None => message.clone(),
+            },
+            message => message.clone(),
+        })
+        .collect();
+    definitions.extend(pending.into_values().map(Message::Parameter));
+    definitions
+}
+
+impl Ulog {
+    // Every segment after the first starts with the definitions, the parameter values
+    // in effect at that point and the active subscriptions, so each is a valid log.
+    // Splits only happen once a segment holds data, so a single oversized message
+    // still ends up in a segment of its own.
+    pub fn split(&self, limit: SplitLimit) -> Result<Vec<Ulog>, FormatError> {
+        let mut timestamps = Timestamps::new(self)?;
+        let mut parameters = BTreeMap::new();
+        for message in &self.definitions {
+            if let Message::Parameter(parameter) = message {
+                parameters.insert(parameter.key.clone(), parameter.clone());
+            }
+        }
+        let mut subscriptions: BTreeMap<u16, MessageAddLogged> = BTreeMap::new();
+        let mut current = self.header.timestamp;
+        let mut segments = Vec::new();
+        let mut segment = Segment::new(self, current, self.definitions.clone(), Vec::new());
+
+        for message in &self.data {
+            timestamps.update(message);
+            let timestamp = timestamps.message(message);
+            if segment.is_full(limit, message, timestamp) {
+                let resubscribed = subscriptions.values().cloned().map(Message::AddLogged);
+                let next = Segment::new(
+                    self,
+                    timestamp.unwrap_or(current),
+                    current_definitions(self, &parameters),
+                    resubscribed.collect(),
+                );
+                segments.push(mem::replace(&mut segment, next));
+            }
+            if let Some(timestamp) = timestamp {
+                current = timestamp;
+            }
+
+            match message {
+                Message::AddLogged(add_logged) => {
+                    subscriptions.insert(add_logged.msg_id, add_logged.clone());
+                }
+                Message::RemoveLogged(remove_logged) => {
+                    subscriptions.remove(&remove_logged.msg_id);
+                }
+                Message::Parameter(parameter) => {
+                    parameters.insert(parameter.key.clone(), parameter.clone());
+                }
+                _ => {}
+            }
+            segment.push(message, timestamp);
+        }
+        segments.push(segment);
+
+        Ok(segments
+            .into_iter()
+            .map(|segment| {
+                let mut ulog = self.rewritten(segment.definitions, segment.data);
+                ulog.header.timestamp = segment.timestamp;
+                ulog
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    DefaultTypes, FormatDefinition, Message, ParameterValue, Sample, Topic, UlogTopic, UlogValue,
+    UlogWriter, WriteError,
+};
+
+const DEFAULT_SYNC_INTERVAL: u64 = 64 * 1024;
+
+#[derive(Debug)]
+pub struct UlogStreamWriter<W> {
+    writer: UlogWriter<W>,
+    sync_interval: u64,
+    last_sync: u64,
+    dropouts: usize,
+    dropout_duration: Duration,
+}
+
+impl<W: Write> UlogStreamWriter<W> {
+    pub fn new(writer: W, timestamp: u64) -> io::Result<Self> {
+        let writer = UlogWriter::new(writer, timestamp)?;
+        Ok(UlogStreamWriter {
+            last_sync: writer.bytes_written(),
+            writer,
+            sync_interval: DEFAULT_SYNC_INTERVAL,
+            dropouts: 0,
+            dropout_duration: Duration::ZERO,
+        })
+    }
+
+    // An interval of 0 disables automatic sync messages.
+    pub fn sync_interval(mut self, bytes: u64) -> Self {
+        self.sync_interval = bytes;
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    pub fn writer(&self) -> &UlogWriter<W> {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    pub fn dropouts(&self) -> usize {
+        self.dropouts
+    }
+
+    pub fn dropout_duration(&self) -> Duration {
+        self.dropout_duration
+    }
+
+    // Sync messages start the data section, so none are inserted among the definitions.
+    fn synced<T>(&mut self, result: Result<T, WriteError>) -> Result<T, WriteError> {
+        let value = result?;
+        let pending = self.writer.bytes_written() - self.last_sync;
+        if self.sync_interval > 0 && self.writer.in_data_section() && pending >= self.sync_interval
+        {
+            self.write_sync()?;
+        }
+        Ok(value)
+    }
+
+    pub fn add_format(&mut self, definition: FormatDefinition) -> Result<(), WriteError> {
+        let result = self.writer.add_format(definition);
+        self.synced(result)
+    }
+
+    pub fn add_info(&mut self, name: &str, value: &UlogValue) -> Result<(), WriteError> {
+        let result = self.writer.add_info(name, value);
+        self.synced(result)
+    }
+
+    pub fn add_info_multiple(
+        &mut self,
+        name: &str,
+        value: &UlogValue,
+        is_continued: bool,
+    ) -> Result<(), WriteError> {
+        let result = self.writer.add_info_multiple(name, value, is_continued);
+        self.synced(result)
+    }
+
+    pub fn add_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), WriteError> {
+        let result = self.writer.add_parameter(name, value);
+        self.synced(result)
+    }
+
+    pub fn add_default_parameter(
+        &mut self,
+        name: &str,
+        value: ParameterValue,
+        default_types: DefaultTypes,
+    ) -> Result<(), WriteError> {
+        let result = self
+            .writer
+            .add_default_parameter(name, value, default_types);
+        self.synced(result)
+    }
+
+    pub fn add_subscription(
+        &mut self,
+        message_name: &str,
+        multi_id: u8,
+    ) -> Result<u16, WriteError> {
+        let result = self.writer.add_subscription(message_name, multi_id);
+        self.synced(result)
+    }
+
+    pub fn add_topic(&mut self, topic: &Topic) -> Result<(), WriteError> {
+        let result = self.writer.add_topic(topic);
+        self.synced(result)
+    }
+
+    pub fn subscribe(&mut self, topic: &Topic, multi_id: u8) -> Result<u16, WriteError> {
+        let result = self.writer.subscribe(topic, multi_id);
+        self.synced(result)
+    }
+
+    pub fn remove_subscription(&mut self, msg_id: u16) -> Result<(), WriteError> {
+        let result = self.writer.remove_subscription(msg_id);
+        self.synced(result)
+    }
+
+    pub fn write_data(&mut self, msg_id: u16, values: &[UlogValue]) -> Result<(), WriteError> {
+        let result = self.writer.write_data(msg_id, values);
+        self.synced(result)
+    }
+
+    pub fn write_data_bytes(&mut self, msg_id: u16, payload: &[u8]) -> Result<(), WriteError> {
+        let result = self.writer.write_data_bytes(msg_id, payload);
+        self.synced(result)
+    }
+
+    pub fn write_sample(&mut self, msg_id: u16, sample: &Sample) -> Result<(), WriteError> {
+        let result = self.writer.write_sample(msg_id, sample);
+        self.synced(result)
+    }
+
+    pub fn write_topic<T: UlogTopic>(&mut self, msg_id: u16, value: &T) -> Result<(), WriteError> {
+        let result = self.writer.write_topic(msg_id, value);
+        self.synced(result)
+    }
+
+    pub fn write_logging(
+        &mut self,
+        log_level: u8,
+        timestamp: u64,
+        message: &str,
+    ) -> Result<(), WriteError> {
+        let result = self.writer.write_logging(log_level, timestamp, message);
+        self.synced(result)
+    }
+
+    pub fn write_tagged_logging(
+        &mut self,
+        log_level: u8,
+        tag: u16,
+        timestamp: u64,
+        message: &str,
+    ) -> Result<(), WriteError> {
+        let result = self
+            .writer
+            .write_tagged_logging(log_level, tag, timestamp, message);
+        self.synced(result)
+    }
+
+    pub fn write_message(&mut self, message: &Message) -> Result<(), WriteError> {
+        self.writer.write_message(message)?;
+        if let Message::Sync(_) = message {
+            self.last_sync = self.writer.bytes_written();
+            return Ok(());
+        }
+        self.synced(Ok(()))
+    }
+
+    pub fn write_sync(&mut self) -> Result<(), WriteError> {
+        self.writer.write_sync()?;
+        self.last_sync = self.writer.bytes_written();
+        Ok(())
+    }
+
+    // Dropouts longer than u16::MAX milliseconds are split over several messages, and
+    // dropouts shorter than a millisecond are not recorded.
+    pub fn record_dropout(&mut self, duration: Duration) -> Result<(), WriteError> {
+        let mut remaining = duration.as_millis();
+        if remaining == 0 {
+            return Ok(());
+        }
+        loop {
+            let chunk = remaining.min(u16::MAX.into()) as u16;
+            self.writer.write_dropout(chunk)?;
+            self.dropout_duration += Duration::from_millis(chunk.into());
+            remaining -= u128::from(chunk);
+            if remaining == 0 {
+                break;
+            }
+        }
+        self.dropouts += 1;
+        self.synced(Ok(()))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        self.writer.finish()
+    }
+}
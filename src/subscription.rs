@@ -0,0 +1,75 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::MessageAddLogged;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub msg_id: u16,
+    pub multi_id: u8,
+    pub message_name: Arc<str>,
+    pub added: usize,
+    pub removed: Option<usize>,
+}
+
+impl Subscription {
+    pub fn is_active_at(&self, index: usize) -> bool {
+        self.added <= index && self.removed.is_none_or(|removed| index < removed)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Subscriptions {
+    pub subscriptions: Vec<Subscription>,
+    active: HashMap<u16, usize>,
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.subscriptions.clear();
+        self.active.clear();
+    }
+
+    pub fn add_logged(&mut self, add_logged: &MessageAddLogged, index: usize) {
+        self.remove_logged(add_logged.msg_id, index);
+        self.active
+            .insert(add_logged.msg_id, self.subscriptions.len());
+        self.subscriptions.push(Subscription {
+            msg_id: add_logged.msg_id,
+            multi_id: add_logged.multi_id,
+            message_name: add_logged.message_name.clone(),
+            added: index,
+            removed: None,
+        });
+    }
+
+    pub fn remove_logged(&mut self, msg_id: u16, index: usize) {
+        if let Some(i) = self.active.remove(&msg_id) {
+            self.subscriptions[i].removed = Some(index);
+        }
+    }
+
+    pub fn get(&self, msg_id: u16) -> Option<&Subscription> {
+        self.active.get(&msg_id).map(|&i| &self.subscriptions[i])
+    }
+
+    pub fn at(&self, msg_id: u16, index: usize) -> Option<&Subscription> {
+        self.subscriptions
+            .iter()
+            .rev()
+            .find(|subscription| subscription.msg_id == msg_id && subscription.is_active_at(index))
+    }
+
+    pub fn find<'a>(
+        &'a self,
+        message_name: &'a str,
+        multi_id: u8,
+    ) -> impl Iterator<Item = &'a Subscription> {
+        self.subscriptions.iter().filter(move |subscription| {
+            *subscription.message_name == *message_name && subscription.multi_id == multi_id
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogTag {
+    Unassigned,
+    MavlinkHandler,
+    PpkHandler,
+    CameraHandler,
+    PtpHandler,
+    SerialHandler,
+    Watchdog,
+    IoService,
+    Cbuf,
+    Ulg,
+    Other(u16),
+}
+
+impl LogTag {
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            LogTag::Unassigned => Some("unassigned"),
+            LogTag::MavlinkHandler => Some("mavlink_handler"),
+            LogTag::PpkHandler => Some("ppk_handler"),
+            LogTag::CameraHandler => Some("camera_handler"),
+            LogTag::PtpHandler => Some("ptp_handler"),
+            LogTag::SerialHandler => Some("serial_handler"),
+            LogTag::Watchdog => Some("watchdog"),
+            LogTag::IoService => Some("io_service"),
+            LogTag::Cbuf => Some("cbuf"),
+            LogTag::Ulg => Some("ulg"),
+            LogTag::Other(_) => None,
+        }
+    }
+}
+
+impl From<u16> for LogTag {
+    fn from(tag: u16) -> Self {
+        match tag {
+            0 => LogTag::Unassigned,
+            1 => LogTag::MavlinkHandler,
+            2 => LogTag::PpkHandler,
+            3 => LogTag::CameraHandler,
+            4 => LogTag::PtpHandler,
+            5 => LogTag::SerialHandler,
+            6 => LogTag::Watchdog,
+            7 => LogTag::IoService,
+            8 => LogTag::Cbuf,
+            9 => LogTag::Ulg,
+            tag => LogTag::Other(tag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TagNames {
+    pub names: HashMap<u16, String>,
+}
+
+impl TagNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, tag: u16, name: impl Into<String>) {
+        self.names.insert(tag, name.into());
+    }
+
+    pub fn name(&self, tag: u16) -> Option<&str> {
+        self.names
+            .get(&tag)
+            .map(String::as_str)
+            .or_else(|| LogTag::from(tag).name())
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use crate::{
+    writer::matches_field, Field, FieldType, FormatDefinition, Formats, Layout, UlogValue,
+    UlogWriter, WriteError,
+};
+
+#[derive(Debug, Clone)]
+pub struct Topic {
+    definition: FormatDefinition,
+}
+
+macro_rules! topic_fields {
+    ($($field:ident, $field_type:ident;)*) => {
+        impl Topic {
+            $(
+                pub fn $field(self, name: &str) -> Self {
+                    self.field(name, FieldType::$field_type, None)
+                }
+            )*
+        }
+    };
+}
+
+topic_fields! {
+    field_i8, Int8;
+    field_u8, UInt8;
+    field_i16, Int16;
+    field_u16, UInt16;
+    field_i32, Int32;
+    field_u32, UInt32;
+    field_i64, Int64;
+    field_u64, UInt64;
+    field_f32, Float;
+    field_f64, Double;
+    field_bool, Bool;
+}
+
+impl Topic {
+    pub fn new(name: &str) -> Self {
+        Topic {
+            definition: FormatDefinition {
+                name: name.to_string(),
+                fields: Vec::new(),
+            },
+        }
+    }
+
+    pub fn field(mut self, name: &str, field_type: FieldType, array_len: Option<usize>) -> Self {
+        self.definition.fields.push(Field {
+            name: name.to_string(),
+            field_type,
+            array_len,
+        });
+        self
+    }
+
+    pub fn array(self, name: &str, field_type: FieldType, len: usize) -> Self {
+        self.field(name, field_type, Some(len))
+    }
+
+    pub fn string(self, name: &str, len: usize) -> Self {
+        self.field(name, FieldType::Char, Some(len))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    pub fn definition(&self) -> &FormatDefinition {
+        &self.definition
+    }
+
+    // Topics only hold builtin field types, so the layout needs no other formats.
+    pub fn layout(&self) -> Result<Layout, WriteError> {
+        let mut formats = Formats::new();
+        formats.insert(self.definition.clone());
+        Ok(formats.layout(&self.definition.name)?)
+    }
+
+    pub fn sample(&self) -> Result<Sample, WriteError> {
+        let layout = self.layout()?;
+        Ok(Sample {
+            payload: vec![0; layout.size],
+            layout: Arc::new(layout),
+        })
+    }
+}
+
+// Implemented by `#[derive(UlogTopic)]`; `encode` writes the fields in declaration
+// order, which is the packed layout of `topic()`.
+pub trait UlogTopic {
+    fn topic() -> Topic;
+    fn encode(&self, output: &mut Vec<u8>);
+}
+
+pub trait UlogField {
+    const FIELD_TYPE: FieldType;
+    const ARRAY_LEN: Option<usize>;
+    fn encode(&self, output: &mut Vec<u8>);
+}
+
+macro_rules! ulog_fields {
+    ($($type:ty, $field_type:ident, $encode:expr;)*) => {
+        $(
+            impl UlogField for $type {
+                const FIELD_TYPE: FieldType = FieldType::$field_type;
+                const ARRAY_LEN: Option<usize> = None;
+                fn encode(&self, output: &mut Vec<u8>) {
+                    let encode: fn(&$type, &mut Vec<u8>) = $encode;
+                    encode(self, output)
+                }
+            }
+
+            impl<const N: usize> UlogField for [$type; N] {
+                const FIELD_TYPE: FieldType = FieldType::$field_type;
+                const ARRAY_LEN: Option<usize> = Some(N);
+                fn encode(&self, output: &mut Vec<u8>) {
+                    for value in self {
+                        UlogField::encode(value, output);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+ulog_fields! {
+    i8, Int8, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    u8, UInt8, |v, output| output.push(*v);
+    i16, Int16, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    u16, UInt16, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    i32, Int32, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    u32, UInt32, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    i64, Int64, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    u64, UInt64, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    f32, Float, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    f64, Double, |v, output| output.extend_from_slice(&v.to_le_bytes());
+    bool, Bool, |v, output| output.push(u8::from(*v));
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    layout: Arc<Layout>,
+    payload: Vec<u8>,
+}
+
+macro_rules! sample_setters {
+    ($($setter:ident, $value:ident, $type:ty;)*) => {
+        impl Sample {
+            $(
+                pub fn $setter(&mut self, name: &str, value: $type) -> Result<&mut Self, WriteError> {
+                    self.set(name, &UlogValue::$value(value))
+                }
+            )*
+        }
+    };
+}
+
+sample_setters! {
+    set_i8, Int8, i8;
+    set_u8, UInt8, u8;
+    set_i16, Int16, i16;
+    set_u16, UInt16, u16;
+    set_i32, Int32, i32;
+    set_u32, UInt32, u32;
+    set_i64, Int64, i64;
+    set_u64, UInt64, u64;
+    set_f32, Float, f32;
+    set_f64, Double, f64;
+    set_bool, Bool, bool;
+}
+
+impl Sample {
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub fn set(&mut self, name: &str, value: &UlogValue) -> Result<&mut Self, WriteError> {
+        let field = self
+            .layout
+            .field(name)
+            .filter(|field| matches_field(field, value))
+            .ok_or_else(|| WriteError::FieldMismatch {
+                message_name: self.layout.name.clone(),
+                field: name.to_string(),
+            })?;
+        let bytes = &mut self.payload[field.offset..field.offset + field.size()];
+        bytes.fill(0);
+        let mut encoded = Vec::with_capacity(bytes.len());
+        value.encode(&mut encoded);
+        bytes[..encoded.len()].copy_from_slice(&encoded);
+        Ok(self)
+    }
+
+    pub fn set_string(&mut self, name: &str, value: &str) -> Result<&mut Self, WriteError> {
+        self.set(name, &UlogValue::String(value.to_string()))
+    }
+}
+
+impl<W: std::io::Write> UlogWriter<W> {
+    pub fn add_topic(&mut self, topic: &Topic) -> Result<(), WriteError> {
+        self.add_format(topic.definition.clone())
+    }
+
+    // Registers the topic's format first if it is not known yet.
+    pub fn subscribe(&mut self, topic: &Topic, multi_id: u8) -> Result<u16, WriteError> {
+        if self.decoder().formats.get(topic.name()) != Some(&topic.definition) {
+            self.add_topic(topic)?;
+        }
+        self.add_subscription(topic.name(), multi_id)
+    }
+
+    pub fn write_sample(&mut self, msg_id: u16, sample: &Sample) -> Result<(), WriteError> {
+        let layout = self
+            .decoder()
+            .layout(msg_id)
+            .ok_or(WriteError::UnknownMsgId(msg_id))?;
+        if **layout != *sample.layout {
+            let field = layout
+                .fields
+                .iter()
+                .zip(&sample.layout.fields)
+                .find(|(expected, found)| expected != found)
+                .map_or(&sample.layout.name, |(expected, _)| &expected.name);
+            return Err(WriteError::FieldMismatch {
+                message_name: layout.name.clone(),
+                field: field.clone(),
+            });
+        }
+        self.write_data_bytes(msg_id, &sample.payload)
+    }
+
+    pub fn write_topic<T: UlogTopic>(&mut self, msg_id: u16, value: &T) -> Result<(), WriteError> {
+        let mut payload = Vec::new();
+        value.encode(&mut payload);
+        self.write_data_bytes(msg_id, &payload)
+    }
+}
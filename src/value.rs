@@ -0,0 +1,365 @@
+use std::fmt;
+
+use nom::{
+    bytes::complete::take,
+    error::ErrorKind,
+    multi::count,
+    number::complete::{le_f32, le_f64, le_i16, le_i32, le_i64, le_i8, le_u16, le_u32, le_u64, u8},
+};
+
+use crate::{FieldType, ParseError, ParseResult, UlogErrorKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UlogValue {
+    Int8(i8),
+    UInt8(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    Char(char),
+    Int8Array(Vec<i8>),
+    UInt8Array(Vec<u8>),
+    Int16Array(Vec<i16>),
+    UInt16Array(Vec<u16>),
+    Int32Array(Vec<i32>),
+    UInt32Array(Vec<u32>),
+    Int64Array(Vec<i64>),
+    UInt64Array(Vec<u64>),
+    FloatArray(Vec<f32>),
+    DoubleArray(Vec<f64>),
+    BoolArray(Vec<bool>),
+    CharArray(Vec<char>),
+    String(String),
+}
+
+impl UlogValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            UlogValue::Int8(v) => Some(v.into()),
+            UlogValue::UInt8(v) => Some(v.into()),
+            UlogValue::Int16(v) => Some(v.into()),
+            UlogValue::UInt16(v) => Some(v.into()),
+            UlogValue::Int32(v) => Some(v.into()),
+            UlogValue::UInt32(v) => Some(v.into()),
+            UlogValue::Int64(v) => Some(v as f64),
+            UlogValue::UInt64(v) => Some(v as f64),
+            UlogValue::Float(v) => Some(v.into()),
+            UlogValue::Double(v) => Some(v),
+            UlogValue::Bool(v) => Some(u8::from(v).into()),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            UlogValue::Int8(v) => Some(v.into()),
+            UlogValue::UInt8(v) => Some(v.into()),
+            UlogValue::Int16(v) => Some(v.into()),
+            UlogValue::UInt16(v) => Some(v.into()),
+            UlogValue::Int32(v) => Some(v.into()),
+            UlogValue::UInt32(v) => Some(v.into()),
+            UlogValue::Int64(v) => Some(v),
+            UlogValue::UInt64(v) => v.try_into().ok(),
+            UlogValue::Bool(v) => Some(v.into()),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            UlogValue::Int8(v) => v.try_into().ok(),
+            UlogValue::UInt8(v) => Some(v.into()),
+            UlogValue::Int16(v) => v.try_into().ok(),
+            UlogValue::UInt16(v) => Some(v.into()),
+            UlogValue::Int32(v) => v.try_into().ok(),
+            UlogValue::UInt32(v) => Some(v.into()),
+            UlogValue::Int64(v) => v.try_into().ok(),
+            UlogValue::UInt64(v) => Some(v),
+            UlogValue::Bool(v) => Some(v.into()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            UlogValue::Bool(v) => Some(v),
+            _ => self.as_i64().map(|v| v != 0),
+        }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        match *self {
+            UlogValue::Char(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            UlogValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.array_len().is_some()
+    }
+
+    pub fn array_len(&self) -> Option<usize> {
+        match self {
+            UlogValue::Int8Array(v) => Some(v.len()),
+            UlogValue::UInt8Array(v) => Some(v.len()),
+            UlogValue::Int16Array(v) => Some(v.len()),
+            UlogValue::UInt16Array(v) => Some(v.len()),
+            UlogValue::Int32Array(v) => Some(v.len()),
+            UlogValue::UInt32Array(v) => Some(v.len()),
+            UlogValue::Int64Array(v) => Some(v.len()),
+            UlogValue::UInt64Array(v) => Some(v.len()),
+            UlogValue::FloatArray(v) => Some(v.len()),
+            UlogValue::DoubleArray(v) => Some(v.len()),
+            UlogValue::BoolArray(v) => Some(v.len()),
+            UlogValue::CharArray(v) => Some(v.len()),
+            _ => None,
+        }
+    }
+
+    pub fn element(&self, index: usize) -> Option<UlogValue> {
+        match self {
+            UlogValue::Int8Array(v) => v.get(index).copied().map(UlogValue::Int8),
+            UlogValue::UInt8Array(v) => v.get(index).copied().map(UlogValue::UInt8),
+            UlogValue::Int16Array(v) => v.get(index).copied().map(UlogValue::Int16),
+            UlogValue::UInt16Array(v) => v.get(index).copied().map(UlogValue::UInt16),
+            UlogValue::Int32Array(v) => v.get(index).copied().map(UlogValue::Int32),
+            UlogValue::UInt32Array(v) => v.get(index).copied().map(UlogValue::UInt32),
+            UlogValue::Int64Array(v) => v.get(index).copied().map(UlogValue::Int64),
+            UlogValue::UInt64Array(v) => v.get(index).copied().map(UlogValue::UInt64),
+            UlogValue::FloatArray(v) => v.get(index).copied().map(UlogValue::Float),
+            UlogValue::DoubleArray(v) => v.get(index).copied().map(UlogValue::Double),
+            UlogValue::BoolArray(v) => v.get(index).copied().map(UlogValue::Bool),
+            UlogValue::CharArray(v) => v.get(index).copied().map(UlogValue::Char),
+            _ => None,
+        }
+    }
+
+    pub fn to_f64_vec(&self) -> Option<Vec<f64>> {
+        let len = self.array_len()?;
+        (0..len)
+            .map(|index| self.element(index)?.as_f64())
+            .collect()
+    }
+}
+
+macro_rules! slice_accessors {
+    ($($name:ident => $type:ty, $array:ident;)*) => {
+        impl UlogValue {
+            $(
+                pub fn $name(&self) -> Option<&[$type]> {
+                    match self {
+                        UlogValue::$array(values) => Some(values),
+                        _ => None,
+                    }
+                }
+            )*
+        }
+    };
+}
+
+slice_accessors! {
+    as_i8_slice => i8, Int8Array;
+    as_u8_slice => u8, UInt8Array;
+    as_i16_slice => i16, Int16Array;
+    as_u16_slice => u16, UInt16Array;
+    as_i32_slice => i32, Int32Array;
+    as_u32_slice => u32, UInt32Array;
+    as_i64_slice => i64, Int64Array;
+    as_u64_slice => u64, UInt64Array;
+    as_f32_slice => f32, FloatArray;
+    as_f64_slice => f64, DoubleArray;
+    as_bool_slice => bool, BoolArray;
+    as_char_slice => char, CharArray;
+}
+
+macro_rules! from_impls {
+    ($($type:ty => $scalar:ident, $array:ident;)*) => {
+        $(
+            impl From<$type> for UlogValue {
+                fn from(value: $type) -> Self {
+                    UlogValue::$scalar(value)
+                }
+            }
+
+            impl From<Vec<$type>> for UlogValue {
+                fn from(values: Vec<$type>) -> Self {
+                    UlogValue::$array(values)
+                }
+            }
+        )*
+    };
+}
+
+from_impls! {
+    i8 => Int8, Int8Array;
+    u8 => UInt8, UInt8Array;
+    i16 => Int16, Int16Array;
+    u16 => UInt16, UInt16Array;
+    i32 => Int32, Int32Array;
+    u32 => UInt32, UInt32Array;
+    i64 => Int64, Int64Array;
+    u64 => UInt64, UInt64Array;
+    f32 => Float, FloatArray;
+    f64 => Double, DoubleArray;
+    bool => Bool, BoolArray;
+    char => Char, CharArray;
+}
+
+macro_rules! encode_impls {
+    ($($scalar:ident, $array:ident;)*) => {
+        impl UlogValue {
+            pub fn field_type(&self) -> FieldType {
+                match self {
+                    $(UlogValue::$scalar(_) | UlogValue::$array(_) => FieldType::$scalar,)*
+                    UlogValue::Bool(_) | UlogValue::BoolArray(_) => FieldType::Bool,
+                    UlogValue::Char(_) | UlogValue::CharArray(_) | UlogValue::String(_) => {
+                        FieldType::Char
+                    }
+                }
+            }
+
+            pub fn encode(&self, output: &mut Vec<u8>) {
+                match self {
+                    $(
+                        UlogValue::$scalar(v) => output.extend_from_slice(&v.to_le_bytes()),
+                        UlogValue::$array(values) => {
+                            for v in values {
+                                output.extend_from_slice(&v.to_le_bytes());
+                            }
+                        }
+                    )*
+                    UlogValue::Bool(v) => output.push(u8::from(*v)),
+                    UlogValue::BoolArray(values) => output.extend(values.iter().map(|&v| u8::from(v))),
+                    UlogValue::Char(v) => output.push(*v as u8),
+                    UlogValue::CharArray(values) => output.extend(values.iter().map(|&v| v as u8)),
+                    UlogValue::String(v) => output.extend_from_slice(v.as_bytes()),
+                }
+            }
+        }
+    };
+}
+
+encode_impls! {
+    Int8, Int8Array;
+    UInt8, UInt8Array;
+    Int16, Int16Array;
+    UInt16, UInt16Array;
+    Int32, Int32Array;
+    UInt32, UInt32Array;
+    Int64, Int64Array;
+    UInt64, UInt64Array;
+    Float, FloatArray;
+    Double, DoubleArray;
+}
+
+fn write_array<T: fmt::Display>(f: &mut fmt::Formatter<'_>, values: &[T]) -> fmt::Result {
+    write!(f, "[")?;
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", value)?;
+    }
+    write!(f, "]")
+}
+
+impl fmt::Display for UlogValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UlogValue::Int8(v) => write!(f, "{}", v),
+            UlogValue::UInt8(v) => write!(f, "{}", v),
+            UlogValue::Int16(v) => write!(f, "{}", v),
+            UlogValue::UInt16(v) => write!(f, "{}", v),
+            UlogValue::Int32(v) => write!(f, "{}", v),
+            UlogValue::UInt32(v) => write!(f, "{}", v),
+            UlogValue::Int64(v) => write!(f, "{}", v),
+            UlogValue::UInt64(v) => write!(f, "{}", v),
+            UlogValue::Float(v) => write!(f, "{}", v),
+            UlogValue::Double(v) => write!(f, "{}", v),
+            UlogValue::Bool(v) => write!(f, "{}", v),
+            UlogValue::Char(v) => write!(f, "{}", v),
+            UlogValue::Int8Array(v) => write_array(f, v),
+            UlogValue::UInt8Array(v) => write_array(f, v),
+            UlogValue::Int16Array(v) => write_array(f, v),
+            UlogValue::UInt16Array(v) => write_array(f, v),
+            UlogValue::Int32Array(v) => write_array(f, v),
+            UlogValue::UInt32Array(v) => write_array(f, v),
+            UlogValue::Int64Array(v) => write_array(f, v),
+            UlogValue::UInt64Array(v) => write_array(f, v),
+            UlogValue::FloatArray(v) => write_array(f, v),
+            UlogValue::DoubleArray(v) => write_array(f, v),
+            UlogValue::BoolArray(v) => write_array(f, v),
+            UlogValue::CharArray(v) => write_array(f, v),
+            UlogValue::String(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+fn bool(input: &[u8]) -> ParseResult<'_, bool> {
+    u8(input).map(|(input, v)| (input, v != 0))
+}
+
+fn char(input: &[u8]) -> ParseResult<'_, char> {
+    u8(input).map(|(input, v)| (input, v as char))
+}
+
+pub fn string(input: &[u8], len: usize) -> ParseResult<'_, UlogValue> {
+    let (input, bytes) = take(len)(input)?;
+    let end = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    let bytes = bytes[..end].trim_ascii_end();
+    let value = match std::str::from_utf8(bytes) {
+        Ok(s) => UlogValue::String(s.to_string()),
+        Err(_) => UlogValue::CharArray(bytes.iter().map(|&c| c as char).collect()),
+    };
+    Ok((input, value))
+}
+
+macro_rules! decode {
+    ($input:expr, $array_len:expr, $parser:expr) => {
+        match $array_len {
+            Some(len) => count($parser, len)($input).map(|(input, v)| (input, v.into())),
+            None => $parser($input).map(|(input, v)| (input, v.into())),
+        }
+    };
+}
+
+pub fn value<'a>(
+    input: &'a [u8],
+    field_type: &FieldType,
+    array_len: Option<usize>,
+) -> ParseResult<'a, UlogValue> {
+    match field_type {
+        FieldType::Int8 => decode!(input, array_len, le_i8),
+        FieldType::UInt8 => decode!(input, array_len, u8),
+        FieldType::Int16 => decode!(input, array_len, le_i16),
+        FieldType::UInt16 => decode!(input, array_len, le_u16),
+        FieldType::Int32 => decode!(input, array_len, le_i32),
+        FieldType::UInt32 => decode!(input, array_len, le_u32),
+        FieldType::Int64 => decode!(input, array_len, le_i64),
+        FieldType::UInt64 => decode!(input, array_len, le_u64),
+        FieldType::Float => decode!(input, array_len, le_f32),
+        FieldType::Double => decode!(input, array_len, le_f64),
+        FieldType::Bool => decode!(input, array_len, bool),
+        FieldType::Char => match array_len {
+            Some(len) => string(input, len),
+            None => decode!(input, array_len, char),
+        },
+        FieldType::Nested(_) => Err(nom::Err::Error(ParseError::new(
+            input,
+            UlogErrorKind::Parse(ErrorKind::Verify),
+        ))),
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use crate::{MsgType, UlogErrorKind};
+
+#[derive(Debug)]
+pub enum ParseWarningKind {
+    Recovered(UlogErrorKind),
+    SyncLost { skipped_bytes: usize },
+    InvalidUtf8,
+    SuspiciousSize { msg_type: MsgType, msg_size: u16 },
+    UnsupportedVersion(u8),
+    UnknownIncompatFlags([u8; 8]),
+}
+
+impl fmt::Display for ParseWarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarningKind::Recovered(kind) => write!(f, "skipped message: {}", kind),
+            ParseWarningKind::SyncLost { skipped_bytes } => write!(
+                f,
+                "corrupt data, skipped {} bytes to the next sync message",
+                skipped_bytes
+            ),
+            ParseWarningKind::InvalidUtf8 => write!(f, "invalid UTF-8 string"),
+            ParseWarningKind::SuspiciousSize { msg_type, msg_size } => {
+                write!(f, "message {} has unexpected size {}", msg_type, msg_size)
+            }
+            ParseWarningKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported ULog version {}, parsing anyway", version)
+            }
+            ParseWarningKind::UnknownIncompatFlags(flags) => write!(
+                f,
+                "unknown incompatible flag bits {:02x?}, parsing anyway",
+                flags
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseWarning {
+    pub kind: ParseWarningKind,
+    pub offset: usize,
+    pub message_index: Option<usize>,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if let Some(message_index) = self.message_index {
+            write!(f, " (message {})", message_index)?;
+        }
+        Ok(())
+    }
+}
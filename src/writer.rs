@@ -0,0 +1,491 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+use crate::{
+    construct::{key_len, parameter_key, typed_key},
+    parse_ulog, starts_data_section, Decoder, DefaultTypes, FormatDefinition, FormatError, Layout,
+    LayoutField, Message, MessageAddLogged, MessageHeader, MsgType, ParameterValue, UlogError,
+    UlogValue, INCOMPAT_FLAG0_DATA_APPENDED_MASK, MAGIC, SYNC_MAGIC, ULOG_VERSION,
+};
+
+#[derive(Debug)]
+pub enum WriteError {
+    Io(io::Error),
+    Format(FormatError),
+    Parse(UlogError),
+    UnknownMsgId(u16),
+    ValueCount {
+        message_name: String,
+        expected: usize,
+        found: usize,
+    },
+    FieldMismatch {
+        message_name: String,
+        field: String,
+    },
+    PayloadSize {
+        message_name: String,
+        len: usize,
+        min: usize,
+        max: usize,
+    },
+    KeyTooLong(String),
+    MessageTooLarge {
+        msg_type: MsgType,
+        size: usize,
+    },
+    FormatInDataSection(String),
+    TooManySubscriptions,
+    UnresolvedDataRef(u16),
+    NoAppendSlot,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Io(err) => write!(f, "I/O error: {}", err),
+            WriteError::Format(err) => write!(f, "{}", err),
+            WriteError::Parse(err) => write!(f, "cannot append to log: {}", err),
+            WriteError::UnknownMsgId(msg_id) => {
+                write!(f, "no subscription for msg_id {}", msg_id)
+            }
+            WriteError::ValueCount {
+                message_name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "format {:?} has {} fields, got {} values",
+                message_name, expected, found
+            ),
+            WriteError::FieldMismatch {
+                message_name,
+                field,
+            } => write!(
+                f,
+                "value for field {:?} of {:?} does not match its type",
+                field, message_name
+            ),
+            WriteError::PayloadSize {
+                message_name,
+                len,
+                min,
+                max,
+            } => write!(
+                f,
+                "payload of {} bytes does not fit format {:?}, which takes {} to {} bytes",
+                len, message_name, min, max
+            ),
+            WriteError::KeyTooLong(key) => write!(f, "key {:?} is longer than 255 bytes", key),
+            WriteError::MessageTooLarge { msg_type, size } => write!(
+                f,
+                "message {} of {} bytes does not fit in a ULog message",
+                msg_type, size
+            ),
+            WriteError::FormatInDataSection(name) => write!(
+                f,
+                "format {:?} must be written before the data section",
+                name
+            ),
+            WriteError::TooManySubscriptions => write!(f, "all msg_ids are in use"),
+            WriteError::UnresolvedDataRef(msg_id) => write!(
+                f,
+                "data for msg_id {} refers to an input that is not available",
+                msg_id
+            ),
+            WriteError::NoAppendSlot => {
+                write!(f, "the flag bits have no free appended_offsets entry")
+            }
+        }
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriteError::Io(err) => Some(err),
+            WriteError::Format(err) => Some(err),
+            WriteError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(err: io::Error) -> Self {
+        WriteError::Io(err)
+    }
+}
+
+impl From<FormatError> for WriteError {
+    fn from(err: FormatError) -> Self {
+        WriteError::Format(err)
+    }
+}
+
+impl From<UlogError> for WriteError {
+    fn from(err: UlogError) -> Self {
+        WriteError::Parse(err)
+    }
+}
+
+pub(crate) fn matches_field(field: &LayoutField, value: &UlogValue) -> bool {
+    if value.field_type() != field.field_type {
+        return false;
+    }
+    match (field.array_len, value) {
+        (Some(len), UlogValue::String(v)) => v.len() <= len,
+        (_, UlogValue::String(_)) => false,
+        (array_len, value) => value.array_len() == array_len,
+    }
+}
+
+// Trailing padding may be left out, so any length between the last field's end and
+// the full size is accepted.
+fn check_payload_size(layout: &Layout, len: usize) -> Result<(), WriteError> {
+    let min = layout.min_size();
+    if (min..=layout.size).contains(&len) {
+        return Ok(());
+    }
+    Err(WriteError::PayloadSize {
+        message_name: layout.name.clone(),
+        len,
+        min,
+        max: layout.size,
+    })
+}
+
+#[derive(Debug)]
+pub struct UlogWriter<W> {
+    writer: W,
+    decoder: Decoder,
+    buffer: Vec<u8>,
+    next_msg_id: u16,
+    in_data_section: bool,
+    bytes_written: u64,
+}
+
+impl<W: Write> UlogWriter<W> {
+    pub fn new(mut writer: W, timestamp: u64) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[ULOG_VERSION])?;
+        writer.write_all(&timestamp.to_le_bytes())?;
+        writer.write_all(&40u16.to_le_bytes())?;
+        writer.write_all(&[MsgType::FlagBits.into()])?;
+        writer.write_all(&[0; 40])?;
+        Ok(UlogWriter {
+            writer,
+            decoder: Decoder::default(),
+            buffer: Vec::new(),
+            next_msg_id: 0,
+            in_data_section: false,
+            bytes_written: (MAGIC.len() + 9 + 3 + 40) as u64,
+        })
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn in_data_section(&self) -> bool {
+        self.in_data_section
+    }
+
+    fn flush_message(&mut self, msg_type: MsgType) -> Result<(), WriteError> {
+        let header = MessageHeader::new(msg_type, self.buffer.len());
+        let Ok(header) = header else {
+            self.buffer.clear();
+            return header.map(|_| ());
+        };
+        self.writer.write_all(&header.msg_size.to_le_bytes())?;
+        self.writer.write_all(&[header.msg_type.into()])?;
+        self.writer.write_all(&self.buffer)?;
+        self.bytes_written += 3 + self.buffer.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn write_key_value(
+        &mut self,
+        msg_type: MsgType,
+        prefix: &[u8],
+        key: &str,
+        value: &[u8],
+    ) -> Result<(), WriteError> {
+        let key_len = key_len(key)?;
+        self.buffer.extend_from_slice(prefix);
+        self.buffer.push(key_len);
+        self.buffer.extend_from_slice(key.as_bytes());
+        self.buffer.extend_from_slice(value);
+        self.flush_message(msg_type)
+    }
+
+    pub fn add_format(&mut self, definition: FormatDefinition) -> Result<(), WriteError> {
+        if self.in_data_section {
+            return Err(WriteError::FormatInDataSection(definition.name));
+        }
+        self.buffer
+            .extend_from_slice(definition.to_string().as_bytes());
+        self.flush_message(MsgType::Format)?;
+        self.decoder.formats.insert(definition);
+        Ok(())
+    }
+
+    pub fn add_info(&mut self, name: &str, value: &UlogValue) -> Result<(), WriteError> {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        self.write_key_value(MsgType::Info, &[], &typed_key(name, value), &bytes)
+    }
+
+    pub fn add_info_multiple(
+        &mut self,
+        name: &str,
+        value: &UlogValue,
+        is_continued: bool,
+    ) -> Result<(), WriteError> {
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        self.write_key_value(
+            MsgType::InfoMultiple,
+            &[u8::from(is_continued)],
+            &typed_key(name, value),
+            &bytes,
+        )
+    }
+
+    pub fn add_parameter(&mut self, name: &str, value: ParameterValue) -> Result<(), WriteError> {
+        let (key, bytes) = parameter_key(name, value);
+        self.write_key_value(MsgType::Parameter, &[], &key, &bytes)
+    }
+
+    pub fn add_default_parameter(
+        &mut self,
+        name: &str,
+        value: ParameterValue,
+        default_types: DefaultTypes,
+    ) -> Result<(), WriteError> {
+        let (key, bytes) = parameter_key(name, value);
+        self.write_key_value(
+            MsgType::ParameterDefault,
+            &[default_types.bits()],
+            &key,
+            &bytes,
+        )
+    }
+
+    pub fn add_subscription(
+        &mut self,
+        message_name: &str,
+        multi_id: u8,
+    ) -> Result<u16, WriteError> {
+        let msg_id = (self.next_msg_id..=u16::MAX)
+            .find(|&msg_id| !self.decoder.is_subscribed(msg_id))
+            .ok_or(WriteError::TooManySubscriptions)?;
+        let add_logged = MessageAddLogged::new(message_name, multi_id, msg_id)?;
+        self.decoder.add_logged(&add_logged)?;
+        self.in_data_section = true;
+        self.buffer.push(multi_id);
+        self.buffer.extend_from_slice(&msg_id.to_le_bytes());
+        self.buffer.extend_from_slice(message_name.as_bytes());
+        self.flush_message(MsgType::AddLogged)?;
+        self.next_msg_id = msg_id.saturating_add(1);
+        Ok(msg_id)
+    }
+
+    pub fn remove_subscription(&mut self, msg_id: u16) -> Result<(), WriteError> {
+        if !self.decoder.is_subscribed(msg_id) {
+            return Err(WriteError::UnknownMsgId(msg_id));
+        }
+        self.decoder.remove_logged(msg_id);
+        self.buffer.extend_from_slice(&msg_id.to_le_bytes());
+        self.flush_message(MsgType::RemoveLogged)
+    }
+
+    pub fn write_data(&mut self, msg_id: u16, values: &[UlogValue]) -> Result<(), WriteError> {
+        let layout = self
+            .decoder
+            .layout(msg_id)
+            .ok_or(WriteError::UnknownMsgId(msg_id))?
+            .clone();
+        if values.len() != layout.fields.len() {
+            return Err(WriteError::ValueCount {
+                message_name: layout.name.clone(),
+                expected: layout.fields.len(),
+                found: values.len(),
+            });
+        }
+        self.buffer.extend_from_slice(&msg_id.to_le_bytes());
+        self.buffer.resize(2 + layout.size, 0);
+        let mut bytes = Vec::new();
+        for (field, value) in layout.fields.iter().zip(values) {
+            if !matches_field(field, value) {
+                self.buffer.clear();
+                return Err(WriteError::FieldMismatch {
+                    message_name: layout.name.clone(),
+                    field: field.name.clone(),
+                });
+            }
+            bytes.clear();
+            value.encode(&mut bytes);
+            let start = 2 + field.offset;
+            self.buffer[start..start + bytes.len()].copy_from_slice(&bytes);
+        }
+        self.flush_message(MsgType::Data)
+    }
+
+    pub fn write_data_bytes(&mut self, msg_id: u16, payload: &[u8]) -> Result<(), WriteError> {
+        let layout = self
+            .decoder
+            .layout(msg_id)
+            .ok_or(WriteError::UnknownMsgId(msg_id))?;
+        check_payload_size(layout, payload.len())?;
+        self.buffer.extend_from_slice(&msg_id.to_le_bytes());
+        self.buffer.extend_from_slice(payload);
+        self.flush_message(MsgType::Data)
+    }
+
+    pub fn write_logging(
+        &mut self,
+        log_level: u8,
+        timestamp: u64,
+        message: &str,
+    ) -> Result<(), WriteError> {
+        self.in_data_section = true;
+        self.buffer.push(log_level);
+        self.buffer.extend_from_slice(&timestamp.to_le_bytes());
+        self.buffer.extend_from_slice(message.as_bytes());
+        self.flush_message(MsgType::Logging)
+    }
+
+    pub fn write_tagged_logging(
+        &mut self,
+        log_level: u8,
+        tag: u16,
+        timestamp: u64,
+        message: &str,
+    ) -> Result<(), WriteError> {
+        self.in_data_section = true;
+        self.buffer.push(log_level);
+        self.buffer.extend_from_slice(&tag.to_le_bytes());
+        self.buffer.extend_from_slice(&timestamp.to_le_bytes());
+        self.buffer.extend_from_slice(message.as_bytes());
+        self.flush_message(MsgType::LoggingTagged)
+    }
+
+    pub fn write_sync(&mut self) -> Result<(), WriteError> {
+        self.in_data_section = true;
+        self.buffer.extend_from_slice(&SYNC_MAGIC);
+        self.flush_message(MsgType::Sync)
+    }
+
+    pub fn write_dropout(&mut self, duration: u16) -> Result<(), WriteError> {
+        self.in_data_section = true;
+        self.buffer.extend_from_slice(&duration.to_le_bytes());
+        self.flush_message(MsgType::Dropout)
+    }
+
+    pub fn write_message(&mut self, message: &Message) -> Result<(), WriteError> {
+        match message {
+            Message::Format(format) => {
+                let definition = format.definition().map_err(|error| FormatError::Invalid {
+                    format: format.format.clone(),
+                    error,
+                })?;
+                if self.in_data_section {
+                    return Err(WriteError::FormatInDataSection(definition.name));
+                }
+                self.decoder.formats.insert(definition);
+            }
+            Message::AddLogged(add_logged) => self.decoder.add_logged(add_logged)?,
+            Message::RemoveLogged(remove_logged) => {
+                self.decoder.remove_logged(remove_logged.msg_id)
+            }
+            Message::Data(data) => {
+                let layout = self
+                    .decoder
+                    .layout(data.msg_id)
+                    .ok_or(WriteError::UnknownMsgId(data.msg_id))?;
+                check_payload_size(layout, data.data.len())?;
+            }
+            _ => {}
+        }
+        if starts_data_section(message.header().msg_type) {
+            self.in_data_section = true;
+        }
+        message.encode(&mut self.buffer)?;
+        self.writer.write_all(&self.buffer)?;
+        self.bytes_written += self.buffer.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// The flag bits start right after the 16-byte file header and a 3-byte message header.
+const APPENDED_OFFSETS_POSITION: u64 = 16 + 3 + 16;
+const INCOMPAT_FLAGS_POSITION: u64 = 16 + 3 + 8;
+
+impl<W: Read + Write + Seek> UlogWriter<W> {
+    // Marks the data appended from here on in the flag bits, so a log cut off by a
+    // crash stays readable. Existing subscriptions stay valid for `write_data`.
+    pub fn append(mut writer: W) -> Result<Self, WriteError> {
+        writer.seek(SeekFrom::Start(0))?;
+        let mut input = Vec::new();
+        writer.read_to_end(&mut input)?;
+        let ulog = parse_ulog(&input)?;
+        let flag_bits = &ulog.message_flag_bits;
+        let slot = flag_bits
+            .appended_offsets
+            .iter()
+            .position(|&offset| offset == 0)
+            .filter(|_| flag_bits.header.msg_size >= 40)
+            .ok_or(WriteError::NoAppendSlot)?;
+
+        let mut decoder = Decoder::new(ulog.formats()?);
+        for message in &ulog.data {
+            match message {
+                Message::AddLogged(add_logged) => decoder.add_logged(add_logged)?,
+                Message::RemoveLogged(remove_logged) => decoder.remove_logged(remove_logged.msg_id),
+                _ => {}
+            }
+        }
+
+        let end = input.len() as u64;
+        writer.seek(SeekFrom::Start(INCOMPAT_FLAGS_POSITION))?;
+        writer.write_all(&[flag_bits.incompat_flags[0] | INCOMPAT_FLAG0_DATA_APPENDED_MASK])?;
+        writer.seek(SeekFrom::Start(APPENDED_OFFSETS_POSITION + 8 * slot as u64))?;
+        writer.write_all(&end.to_le_bytes())?;
+        writer.seek(SeekFrom::Start(end))?;
+        Ok(UlogWriter {
+            writer,
+            decoder,
+            buffer: Vec::new(),
+            next_msg_id: 0,
+            in_data_section: true,
+            bytes_written: end,
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, LitStr};
+
+// `VehicleStatus` becomes `vehicle_status`, and `GPSPosition` becomes `gps_position`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+// Reads `#[ulog(<key> = "...")]`, rejecting any other key.
+fn ulog_attribute(attrs: &[Attribute], key: &str) -> syn::Result<Option<LitStr>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("ulog")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                value = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error(format!("expected `{}`", key)))
+            }
+        })?;
+    }
+    Ok(value)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "UlogTopic can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "UlogTopic needs a struct with named fields",
+        ));
+    };
+
+    let topic_name = match ulog_attribute(&input.attrs, "name")? {
+        Some(name) => name.value(),
+        None => snake_case(&input.ident.to_string()),
+    };
+    let mut definitions = Vec::new();
+    let mut encodings = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named fields have an ident");
+        let ty = &field.ty;
+        let name = match ulog_attribute(&field.attrs, "rename")? {
+            Some(name) => name.value(),
+            None => ident.to_string(),
+        };
+        definitions.push(quote! {
+            .field(
+                #name,
+                <#ty as ::ulogrs::UlogField>::FIELD_TYPE,
+                <#ty as ::ulogrs::UlogField>::ARRAY_LEN,
+            )
+        });
+        encodings.push(quote! {
+            ::ulogrs::UlogField::encode(&self.#ident, output);
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ulogrs::UlogTopic for #ident #ty_generics #where_clause {
+            fn topic() -> ::ulogrs::Topic {
+                ::ulogrs::Topic::new(#topic_name) #(#definitions)*
+            }
+
+            fn encode(&self, output: &mut ::std::vec::Vec<u8>) {
+                #(#encodings)*
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(UlogTopic, attributes(ulog))]
+pub fn derive_ulog_topic(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,366 @@
+#![no_std]
+
+use core::fmt;
+
+pub const MAGIC: [u8; 7] = [0x55, 0x4c, 0x6f, 0x67, 0x01, 0x12, 0x35];
+pub const ULOG_VERSION: u8 = 1;
+pub const SYNC_MAGIC: [u8; 8] = [0x2f, 0x73, 0x13, 0x20, 0x25, 0x0c, 0xbb, 0x12];
+
+const FLAG_BITS: u8 = b'B';
+const FORMAT: u8 = b'F';
+const INFO: u8 = b'I';
+const INFO_MULTIPLE: u8 = b'M';
+const PARAMETER: u8 = b'P';
+const PARAMETER_DEFAULT: u8 = b'Q';
+const ADD_LOGGED: u8 = b'A';
+const REMOVE_LOGGED: u8 = b'R';
+const DATA: u8 = b'D';
+const LOGGING: u8 = b'L';
+const LOGGING_TAGGED: u8 = b'C';
+const SYNC: u8 = b'S';
+const DROPOUT: u8 = b'O';
+
+pub trait ByteSink {
+    type Error;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    // Fails if `len` more bytes cannot be written, so that a message is either written
+    // whole or not at all. Sinks that cannot tell accept everything.
+    fn reserve(&mut self, _len: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<S: ByteSink> ByteSink for &mut S {
+    type Error = S::Error;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        (**self).write(bytes)
+    }
+
+    fn reserve(&mut self, len: usize) -> Result<(), Self::Error> {
+        (**self).reserve(len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SliceFull;
+
+#[derive(Debug)]
+pub struct SliceSink<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        SliceSink { buffer, len: 0 }
+    }
+
+    pub fn written(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl ByteSink for SliceSink<'_> {
+    type Error = SliceFull;
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        let end = self.len + bytes.len();
+        let target = self.buffer.get_mut(self.len..end).ok_or(SliceFull)?;
+        target.copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn reserve(&mut self, len: usize) -> Result<(), Self::Error> {
+        if self.buffer.len() - self.len < len {
+            return Err(SliceFull);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteError<E> {
+    Sink(E),
+    KeyTooLong,
+    MessageTooLarge,
+    FormatInDataSection,
+}
+
+impl<E: fmt::Display> fmt::Display for WriteError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Sink(err) => write!(f, "sink error: {}", err),
+            WriteError::KeyTooLong => write!(f, "key is longer than 255 bytes"),
+            WriteError::MessageTooLarge => write!(f, "message does not fit in a ULog message"),
+            WriteError::FormatInDataSection => {
+                write!(f, "formats must be written before the data section")
+            }
+        }
+    }
+}
+
+// Formats `value` into the tail of `buffer`, without allocating.
+fn decimal(mut value: usize, buffer: &mut [u8; 20]) -> &[u8] {
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    &buffer[start..]
+}
+
+#[derive(Debug)]
+pub struct UlogWriter<S> {
+    sink: S,
+    in_data_section: bool,
+    bytes_written: u64,
+}
+
+impl<S: ByteSink> UlogWriter<S> {
+    pub fn new(mut sink: S, timestamp: u64) -> Result<Self, WriteError<S::Error>> {
+        sink.reserve(MAGIC.len() + 9 + 3 + 40)
+            .map_err(WriteError::Sink)?;
+        sink.write(&MAGIC).map_err(WriteError::Sink)?;
+        sink.write(&[ULOG_VERSION]).map_err(WriteError::Sink)?;
+        sink.write(&timestamp.to_le_bytes())
+            .map_err(WriteError::Sink)?;
+        let mut writer = UlogWriter {
+            sink,
+            in_data_section: false,
+            bytes_written: (MAGIC.len() + 9) as u64,
+        };
+        writer.write_message(FLAG_BITS, &[&[0; 40]])?;
+        Ok(writer)
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    // The message is streamed part by part, so no buffer of its full size is needed.
+    fn write_message(&mut self, msg_type: u8, parts: &[&[u8]]) -> Result<(), WriteError<S::Error>> {
+        let size: usize = parts.iter().map(|part| part.len()).sum();
+        let size = u16::try_from(size).map_err(|_| WriteError::MessageTooLarge)?;
+        self.sink
+            .reserve(3 + usize::from(size))
+            .map_err(WriteError::Sink)?;
+        self.sink
+            .write(&size.to_le_bytes())
+            .map_err(WriteError::Sink)?;
+        self.sink.write(&[msg_type]).map_err(WriteError::Sink)?;
+        for part in parts {
+            self.sink.write(part).map_err(WriteError::Sink)?;
+        }
+        self.bytes_written += 3 + u64::from(size);
+        Ok(())
+    }
+
+    fn write_key_value(
+        &mut self,
+        msg_type: u8,
+        prefix: &[u8],
+        key: &[&[u8]],
+        value: &[u8],
+    ) -> Result<(), WriteError<S::Error>> {
+        let key_len: usize = key.iter().map(|part| part.len()).sum();
+        let key_len = [u8::try_from(key_len).map_err(|_| WriteError::KeyTooLong)?];
+        let mut parts: [&[u8]; 7] = [&[]; 7];
+        parts[0] = prefix;
+        parts[1] = &key_len;
+        parts[2..2 + key.len()].copy_from_slice(key);
+        parts[2 + key.len()] = value;
+        self.write_message(msg_type, &parts[..3 + key.len()])
+    }
+
+    pub fn add_format(&mut self, format: &str) -> Result<(), WriteError<S::Error>> {
+        if self.in_data_section {
+            return Err(WriteError::FormatInDataSection);
+        }
+        self.write_message(FORMAT, &[format.as_bytes()])
+    }
+
+    pub fn add_info(&mut self, key: &str, value: &[u8]) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(INFO, &[], &[key.as_bytes()], value)
+    }
+
+    pub fn add_info_str(&mut self, name: &str, value: &str) -> Result<(), WriteError<S::Error>> {
+        let mut digits = [0; 20];
+        let len = decimal(value.len(), &mut digits);
+        self.write_key_value(
+            INFO,
+            &[],
+            &[b"char[", len, b"] ", name.as_bytes()],
+            value.as_bytes(),
+        )
+    }
+
+    pub fn add_info_u32(&mut self, name: &str, value: u32) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            INFO,
+            &[],
+            &[b"uint32_t ", name.as_bytes()],
+            &value.to_le_bytes(),
+        )
+    }
+
+    pub fn add_info_i32(&mut self, name: &str, value: i32) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            INFO,
+            &[],
+            &[b"int32_t ", name.as_bytes()],
+            &value.to_le_bytes(),
+        )
+    }
+
+    pub fn add_info_multiple(
+        &mut self,
+        key: &str,
+        value: &[u8],
+        is_continued: bool,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            INFO_MULTIPLE,
+            &[u8::from(is_continued)],
+            &[key.as_bytes()],
+            value,
+        )
+    }
+
+    pub fn add_parameter_i32(
+        &mut self,
+        name: &str,
+        value: i32,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            PARAMETER,
+            &[],
+            &[b"int32_t ", name.as_bytes()],
+            &value.to_le_bytes(),
+        )
+    }
+
+    pub fn add_parameter_f32(
+        &mut self,
+        name: &str,
+        value: f32,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            PARAMETER,
+            &[],
+            &[b"float ", name.as_bytes()],
+            &value.to_le_bytes(),
+        )
+    }
+
+    pub fn add_default_parameter_i32(
+        &mut self,
+        name: &str,
+        value: i32,
+        default_types: u8,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            PARAMETER_DEFAULT,
+            &[default_types],
+            &[b"int32_t ", name.as_bytes()],
+            &value.to_le_bytes(),
+        )
+    }
+
+    pub fn add_default_parameter_f32(
+        &mut self,
+        name: &str,
+        value: f32,
+        default_types: u8,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.write_key_value(
+            PARAMETER_DEFAULT,
+            &[default_types],
+            &[b"float ", name.as_bytes()],
+            &value.to_le_bytes(),
+        )
+    }
+
+    // Firmware usually knows its topics up front, so msg_ids are chosen by the caller.
+    pub fn add_subscription(
+        &mut self,
+        message_name: &str,
+        multi_id: u8,
+        msg_id: u16,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.in_data_section = true;
+        self.write_message(
+            ADD_LOGGED,
+            &[&[multi_id], &msg_id.to_le_bytes(), message_name.as_bytes()],
+        )
+    }
+
+    pub fn remove_subscription(&mut self, msg_id: u16) -> Result<(), WriteError<S::Error>> {
+        self.write_message(REMOVE_LOGGED, &[&msg_id.to_le_bytes()])
+    }
+
+    pub fn write_data(&mut self, msg_id: u16, payload: &[u8]) -> Result<(), WriteError<S::Error>> {
+        self.write_message(DATA, &[&msg_id.to_le_bytes(), payload])
+    }
+
+    pub fn write_logging(
+        &mut self,
+        log_level: u8,
+        timestamp: u64,
+        message: &str,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.in_data_section = true;
+        self.write_message(
+            LOGGING,
+            &[&[log_level], &timestamp.to_le_bytes(), message.as_bytes()],
+        )
+    }
+
+    pub fn write_tagged_logging(
+        &mut self,
+        log_level: u8,
+        tag: u16,
+        timestamp: u64,
+        message: &str,
+    ) -> Result<(), WriteError<S::Error>> {
+        self.in_data_section = true;
+        self.write_message(
+            LOGGING_TAGGED,
+            &[
+                &[log_level],
+                &tag.to_le_bytes(),
+                &timestamp.to_le_bytes(),
+                message.as_bytes(),
+            ],
+        )
+    }
+
+    pub fn write_sync(&mut self) -> Result<(), WriteError<S::Error>> {
+        self.in_data_section = true;
+        self.write_message(SYNC, &[&SYNC_MAGIC])
+    }
+
+    pub fn write_dropout(&mut self, duration: u16) -> Result<(), WriteError<S::Error>> {
+        self.in_data_section = true;
+        self.write_message(DROPOUT, &[&duration.to_le_bytes()])
+    }
+}